@@ -1,12 +1,19 @@
-use crate::constants::{STOP_CHASING_RANGE, SWING_LEFT, SWING_RIGHT, SWORD_STUB};
-use crate::force::Force;
+use crate::constants::{
+    STOP_CHASING_RANGE, SWING_LEFT, SWING_RIGHT, SWORD_STUB, UNIT_TYPE_SWORDMAN,
+    WAYPOINT_REACHED_RANGE,
+};
+use crate::difficulty::Difficulty;
+use crate::force::{Factions, Force};
+use crate::game_state::GameplaySet;
 use crate::global_entity_map::GlobalEntityMap;
+use crate::squad::SquadMember;
 use crate::stun::Stun;
 use crate::unit::UnitType;
 use crate::weapon::GearSet;
 use crate::{Player, Unit};
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::Velocity;
+use serde::Deserialize;
 
 #[derive(Component)]
 pub struct TargetDetector {
@@ -16,7 +23,7 @@ pub struct TargetDetector {
     pub lock_type: LockType,
 }
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Debug, Clone, Copy, Deserialize)]
 pub enum LockType {
     Lock,
     Free,
@@ -34,6 +41,10 @@ impl AIOption {
     }
 }
 
+/// Pauses movement and attacks without removing the AI state
+#[derive(Component)]
+pub struct AISuspended;
+
 #[derive(Component)]
 pub struct AI {
     pub option_queue: Vec<AIOption>,
@@ -78,15 +89,20 @@ impl Default for AI {
 pub fn ai_target_detection_system(
     mut ai_query: Query<(&mut TargetDetector, &Transform, &Force, Entity)>,
     potential_targets_query: Query<(Entity, &Transform, &Force)>,
+    factions: Res<Factions>,
 ) {
     for (mut ai_brain, ai_transform, ai_force, ai_entity) in ai_query.iter_mut() {
         let mut closest_target: Option<(Entity, f32)> = None;
 
-        // Check all potential targets with different force
+        // Check all potential targets with hostile force
         for (target_entity, target_transform, target_force) in potential_targets_query.iter() {
             // info!("Checking potential target: {:?}", target_entity);
-            // Skip if same force (don't target allies)
-            if ai_force.force == target_force.force {
+            // Skip unless hostile (don't target allies or neutrals)
+            if !factions.is_hostile(ai_force, target_force) {
+                // Drop a target whose force is no longer hostile
+                if ai_brain.target == target_entity {
+                    ai_brain.target = Entity::PLACEHOLDER;
+                }
                 continue;
             }
 
@@ -132,12 +148,24 @@ pub fn ai_target_detection_system(
 
 // System to move AI entities towards their targets
 pub fn ai_movement_system(
-    mut ai_query: Query<(&TargetDetector, &mut Transform, &mut Velocity, &Unit, Entity), With<AI>>,
-    target_query: Query<&Transform, Without<AI>>, // Query for target transforms
+    mut ai_query: Query<
+        (
+            &TargetDetector,
+            &Transform,
+            &mut Velocity,
+            &Unit,
+            Entity,
+            Option<&SquadMember>,
+        ),
+        (With<AI>, Without<AISuspended>),
+    >,
+    // GlobalTransform so other AI units (companions, enemies) can be targets too
+    target_query: Query<&GlobalTransform>,
     stun_query: Query<&Stun>,
     time: Res<Time>,
 ) {
-    for (ai_brain, mut ai_transform, mut velocity, unit, ai_entity) in ai_query.iter_mut() {
+    for (ai_brain, ai_transform, mut velocity, unit, ai_entity, squad_member) in ai_query.iter_mut()
+    {
         // Skip if AI entity is stunned
         if stun_query.get(ai_entity).is_ok() {
             continue;
@@ -151,19 +179,34 @@ pub fn ai_movement_system(
         }
 
         if let Ok(target_transform) = target_query.get(ai_brain.target) {
-            let distance = ai_transform
-                .translation
-                .distance(target_transform.translation);
+            let target_translation = target_transform.translation();
+
+            // Members waiting for an attack token hold their slot on the ring instead
+            if let Some(member) = squad_member.filter(|member| !member.has_token) {
+                let slot = member.slot_position(target_translation.xy());
+                let offset = slot - ai_transform.translation.xy();
+                velocity.linvel = if offset.length() > WAYPOINT_REACHED_RANGE {
+                    // Don't overshoot the slot in the last frame
+                    let speed = unit
+                        .speed
+                        .min(offset.length() / time.delta_secs().max(f32::EPSILON));
+                    offset.normalize() * speed
+                } else {
+                    Vec2::ZERO
+                };
+                continue;
+            }
+
+            let distance = ai_transform.translation.distance(target_translation);
             if (distance >= STOP_CHASING_RANGE) {
                 // Calculate direction to target
-                let direction_vector = target_transform.translation - ai_transform.translation;
-                let direction = direction_vector.normalize_or_zero();
-
-                // Apply movement using the unit's speed and delta time
-                let movement_delta = direction * unit.speed * time.delta_secs();
+                let direction_vector = target_translation - ai_transform.translation;
+                let direction = direction_vector.xy().normalize_or_zero();
 
-                // Add delta to the translation
-                ai_transform.translation += movement_delta;
+                // Chase at the unit's speed, the physics step integrates the position
+                velocity.linvel = direction * unit.speed;
+            } else {
+                velocity.linvel = Vec2::ZERO;
             }
         }
     }
@@ -171,18 +214,26 @@ pub fn ai_movement_system(
 
 // Attack system using force-based targeting
 pub fn ai_attack_system(
-    mut ai_query: Query<(&TargetDetector, &Transform, &mut AI, Entity)>,
-    target_query: Query<&Transform, (Without<AI>)>,
+    mut ai_query: Query<
+        (&TargetDetector, &Transform, &mut AI, Entity, Option<&SquadMember>),
+        Without<AISuspended>,
+    >,
+    target_query: Query<&Transform>,
     mut move_events: EventWriter<crate::custom_move::ExecuteMoveEvent>,
     global_entities: ResMut<GlobalEntityMap>,
     stun_query: Query<&Stun>,
 ) {
-    for (ai_brain, ai_transform, mut ai, ai_entity) in ai_query.iter_mut() {
+    for (ai_brain, ai_transform, mut ai, ai_entity, squad_member) in ai_query.iter_mut() {
         // Skip if AI entity is stunned
         if stun_query.get(ai_entity).is_ok() {
             continue;
         }
 
+        // Only attack token holders may swing at the target
+        if squad_member.is_some_and(|member| !member.has_token) {
+            continue;
+        }
+
         // Skip if no valid target
         if ai_brain.target == Entity::PLACEHOLDER {
             continue;
@@ -222,7 +273,7 @@ pub fn initialize_unit_aioptions(mut global_map: ResMut<GlobalEntityMap>) {
     // Insert into global map
     global_map
         .unittype_aioptions
-        .insert(UnitType::SwordMan, moves);
+        .insert(UnitType::new(UNIT_TYPE_SWORDMAN), moves);
 }
 
 // Plugin to register the AI systems
@@ -230,15 +281,24 @@ pub struct AIPlugin;
 
 impl Plugin for AIPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(
-            Update,
-            (
-                ai_target_detection_system,
-                ai_movement_system,
-                ai_attack_system,
+        app.init_resource::<Difficulty>()
+            .add_systems(
+                Update,
+                (
+                    ai_target_detection_system,
+                    crate::companion::companion_target_system,
+                    crate::patrol::resolve_patrol_routes,
+                    crate::squad::assign_attack_tokens,
+                    ai_movement_system,
+                    // After ai_movement_system, which slows down units without a target
+                    crate::patrol::ai_idle_system,
+                    crate::companion::companion_follow_system,
+                    crate::companion::companion_spacing_system,
+                    ai_attack_system,
+                )
+                    .chain()
+                    .in_set(GameplaySet),
             )
-                .chain(),
-        )
-        .add_systems(Startup, initialize_unit_aioptions); // Chain ensures they run in order
+            .add_systems(Startup, initialize_unit_aioptions); // Chain ensures they run in order
     }
 }
@@ -1,4 +1,7 @@
 use crate::{particle::ParticleMaterialAsset, unit::HpChangeEvent};
+use crate::constants::{BERSERKER_FACTOR, BERSERKER_MOVE_SPEED};
+use crate::game_state::GameplaySet;
+use crate::stats::{ModifierSource, StatKind, StatModifier, Stats};
 use bevy::prelude::*;
 use bevy_enoki::{prelude::OneShot, ParticleEffectHandle, ParticleSpawner};
 
@@ -18,7 +21,15 @@ impl Plugin for BerserkerPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<BerserkerHealEvent>()
             .add_event::<BerserkerActiveEvent>()
-            .add_systems(Update, (berserker_lifesteal, berserker_active_handler, sacrificed_hp));
+            .add_systems(
+                Update,
+                (
+                    berserker_lifesteal,
+                    (berserker_active_handler, berserker_stance_modifiers).chain(),
+                    sacrificed_hp,
+                )
+                    .in_set(GameplaySet),
+            );
     }
 }
 
@@ -36,6 +47,25 @@ pub struct BerserkerActiveEvent {
     pub entity: Entity,
 }
 
+/// Stance bonuses while berserker is at level 1: more damage, criticals and speed
+pub fn berserker_stance_modifiers(mut berserker_query: Query<(&Berserker, &mut Stats), Changed<Berserker>>) {
+    for (berserker, mut stats) in berserker_query.iter_mut() {
+        let source = ModifierSource::Berserker;
+        if berserker.level == 1 {
+            stats.set_source(
+                &source,
+                [
+                    StatModifier::percent_multiply(StatKind::Damage, BERSERKER_FACTOR - 1.0, source.clone()),
+                    StatModifier::percent_multiply(StatKind::CritRate, BERSERKER_FACTOR - 1.0, source.clone()),
+                    StatModifier::flat(StatKind::Speed, BERSERKER_MOVE_SPEED, source.clone()),
+                ],
+            );
+        } else {
+            stats.remove_source(&source);
+        }
+    }
+}
+
 /// System that decreases HP every 0.1 seconds when berserker is at level 1
 pub fn sacrificed_hp(
     mut commands: Commands,
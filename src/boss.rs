@@ -0,0 +1,479 @@
+use crate::ai::{AI, AIOption, AISuspended, TargetDetector};
+use crate::constants::*;
+use crate::float_text::{FloatingTextConfig, spawn_floating_text};
+use crate::game_state::GameplaySet;
+use crate::level::level::LevelTransitionEvent;
+use crate::level::tiled::{LevelData, ObjectLayers};
+use crate::level::zone::{EnterZoneEvent, Zone, ZoneKind};
+use crate::particle::ParticleMaterialAsset;
+use crate::respawn::PlayerRespawnedEvent;
+use crate::stats::{StatKind, Stats};
+use crate::unit::{HpChangeEvent, Invulnerable, Unit};
+use crate::Player;
+use bevy::prelude::*;
+use bevy_enoki::prelude::*;
+use bevy_rapier2d::prelude::*;
+use std::time::Duration;
+
+/// One stage of a boss fight, entered when HP drops to `hp_threshold` of max HP
+#[derive(Clone)]
+pub struct BossPhase {
+    pub name: String,
+    pub hp_threshold: f32,
+    pub moves: Vec<AIOption>,
+    pub speed: f32,
+}
+
+#[derive(Component)]
+pub struct Boss {
+    pub name: String,
+    pub phases: Vec<BossPhase>,
+    pub current_phase: usize,
+}
+
+impl Boss {
+    pub fn new(name: impl Into<String>, phases: Vec<BossPhase>) -> Self {
+        Self {
+            name: name.into(),
+            phases,
+            current_phase: 0,
+        }
+    }
+
+    /// Index of the deepest phase whose threshold the given HP ratio has reached
+    pub fn phase_for_ratio(&self, hp_ratio: f32) -> usize {
+        self.phases
+            .iter()
+            .rposition(|phase| hp_ratio <= phase.hp_threshold)
+            .unwrap_or(0)
+    }
+}
+
+/// Cinematic pause between phases; the boss is invulnerable and the camera looks at it
+#[derive(Component)]
+pub struct PhaseTransition {
+    pub timer: Timer,
+}
+
+/// Where the camera should look instead of the player, if anywhere
+#[derive(Resource, Default)]
+pub struct CameraFocus {
+    pub target: Option<Entity>,
+}
+
+/// Region from the BossArena layer that locks when the player walks in.
+///
+/// The object needs `collider=sensor` so entering it sends an `EnterZoneEvent`.
+#[derive(Clone, Debug)]
+pub struct BossArena {
+    pub name: String,
+    pub rect: Rect,
+}
+
+#[derive(Resource, Default)]
+pub struct BossArenas {
+    pub arenas: Vec<BossArena>,
+}
+
+/// The fight currently in progress
+#[derive(Resource, Default)]
+pub struct BossFight {
+    pub boss: Option<Entity>,
+    pub arena: Option<String>,
+}
+
+impl BossFight {
+    pub fn is_active(&self) -> bool {
+        self.boss.is_some()
+    }
+}
+
+/// Wall segment spawned around a locked arena
+#[derive(Component)]
+pub struct ArenaWall;
+
+#[derive(Event)]
+pub struct BossPhaseChangedEvent {
+    pub boss: Entity,
+    pub phase: usize,
+}
+
+#[derive(Event)]
+pub struct BossDefeatedEvent {
+    pub boss: Entity,
+}
+
+pub struct BossPlugin;
+
+impl Plugin for BossPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<CameraFocus>()
+            .init_resource::<BossArenas>()
+            .init_resource::<BossFight>()
+            .add_event::<BossPhaseChangedEvent>()
+            .add_event::<BossDefeatedEvent>()
+            .add_systems(Startup, register_boss_arena_layer_system)
+            .add_systems(
+                Update,
+                (
+                    lock_boss_arena,
+                    engage_alerted_bosses,
+                    boss_phase_system,
+                    update_phase_transitions,
+                    unlock_boss_arena,
+                )
+                    .chain()
+                    .in_set(GameplaySet),
+            )
+            .add_systems(
+                Update,
+                (reset_boss_fight_on_level_change, reset_boss_fight_on_respawn),
+            );
+    }
+}
+
+fn register_boss_arena_layer_system(
+    mut commands: Commands,
+    mut object_layers: ResMut<ObjectLayers>,
+) {
+    let load_boss_arenas_system = commands.register_system(load_boss_arenas);
+    object_layers
+        .loader_systems
+        .insert(BOSS_ARENA_LAYER.to_string(), load_boss_arenas_system);
+    info!("Registered system for layer: {}", BOSS_ARENA_LAYER);
+}
+
+fn load_boss_arenas(
+    object_layers: Res<ObjectLayers>,
+    level_data: Res<LevelData>,
+    mut boss_arenas: ResMut<BossArenas>,
+) {
+    let Some(objects) = object_layers.layer_data.get(BOSS_ARENA_LAYER) else {
+        return;
+    };
+
+    boss_arenas.arenas.clear();
+    for object in objects {
+        let tiled::ObjectShape::Rect { width, height } = object.shape else {
+            warn!(
+                "BossArena object '{}' (id {}) is not a rectangle, skipping",
+                object.name,
+                object.id()
+            );
+            continue;
+        };
+        if ZoneKind::from_properties(&object.properties) != Some(ZoneKind::Sensor) {
+            warn!(
+                "BossArena object '{}' (id {}) has no collider=sensor property and will never lock",
+                object.name,
+                object.id()
+            );
+        }
+
+        // The object origin is its top-left corner; on isometric maps the arena is the
+        // bounding box of the projected rectangle
+        let geometry = &level_data.geometry;
+        let top_left = geometry.object_to_world(Vec2::new(object.x, object.y));
+        let rect = [
+            Vec2::new(object.x + width, object.y),
+            Vec2::new(object.x, object.y + height),
+            Vec2::new(object.x + width, object.y + height),
+        ]
+        .into_iter()
+        .fold(Rect::from_corners(top_left, top_left), |rect, corner| {
+            rect.union_point(geometry.object_to_world(corner))
+        });
+        info!("Loaded boss arena '{}' at {:?}", object.name, rect);
+        boss_arenas.arenas.push(BossArena {
+            name: object.name.clone(),
+            rect,
+        });
+    }
+}
+
+/// Starts the fight when the player walks into the sensor of an arena that holds a living boss
+fn lock_boss_arena(
+    mut commands: Commands,
+    mut enter_events: EventReader<EnterZoneEvent>,
+    mut boss_fight: ResMut<BossFight>,
+    boss_arenas: Res<BossArenas>,
+    zone_query: Query<&Zone>,
+    player_query: Query<(), With<Player>>,
+    boss_query: Query<(Entity, &Transform, &Boss)>,
+) {
+    for event in enter_events.read() {
+        if boss_fight.is_active() || !player_query.contains(event.entity) {
+            continue;
+        }
+        if !zone_query
+            .get(event.zone)
+            .is_ok_and(|zone| zone.layer == BOSS_ARENA_LAYER)
+        {
+            continue;
+        }
+        let Some(arena) = boss_arenas
+            .arenas
+            .iter()
+            .find(|arena| arena.name == event.name)
+        else {
+            continue;
+        };
+        let Some((boss_entity, _, boss)) = boss_query
+            .iter()
+            .find(|(_, transform, _)| arena.rect.contains(transform.translation.xy()))
+        else {
+            continue;
+        };
+
+        info!("Player entered arena '{}'", arena.name);
+        start_boss_fight(&mut commands, &mut boss_fight, boss_entity, boss, Some(arena));
+    }
+}
+
+/// Starts the fight when a boss turns on the player without the arena sensor firing: bosses
+/// outside any arena, e.g. spawned by waves, and bosses spawned into the arena the player is in
+fn engage_alerted_bosses(
+    mut commands: Commands,
+    mut boss_fight: ResMut<BossFight>,
+    boss_arenas: Res<BossArenas>,
+    player_query: Query<&Transform, With<Player>>,
+    boss_query: Query<(Entity, &Transform, &TargetDetector, &Boss), Changed<TargetDetector>>,
+) {
+    for (boss_entity, transform, detector, boss) in boss_query.iter() {
+        if boss_fight.is_active() {
+            return;
+        }
+        let Ok(player_transform) = player_query.get(detector.target) else {
+            continue;
+        };
+        let arena = boss_arenas
+            .arenas
+            .iter()
+            .find(|arena| arena.rect.contains(transform.translation.xy()));
+        // Otherwise the arena sensor starts the fight once the player walks in
+        if arena.is_some_and(|arena| !arena.rect.contains(player_transform.translation.xy())) {
+            continue;
+        }
+
+        info!("{} engaged the player", boss.name);
+        start_boss_fight(&mut commands, &mut boss_fight, boss_entity, boss, arena);
+    }
+}
+
+fn start_boss_fight(
+    commands: &mut Commands,
+    boss_fight: &mut BossFight,
+    boss_entity: Entity,
+    boss: &Boss,
+    arena: Option<&BossArena>,
+) {
+    boss_fight.boss = Some(boss_entity);
+    boss_fight.arena = arena.map(|arena| arena.name.clone());
+    match arena {
+        Some(arena) => {
+            info!("Locking arena '{}' for {}", arena.name, boss.name);
+            spawn_arena_walls(commands, arena.rect);
+        }
+        None => info!("Fighting {} outside any arena", boss.name),
+    }
+}
+
+fn spawn_arena_walls(commands: &mut Commands, rect: Rect) {
+    let half = ARENA_WALL_THICKNESS / 2.0;
+    let center = rect.center();
+    let half_size = rect.half_size();
+    let walls = [
+        // (position, half extents)
+        (Vec2::new(center.x, rect.max.y + half), Vec2::new(half_size.x + ARENA_WALL_THICKNESS, half)),
+        (Vec2::new(center.x, rect.min.y - half), Vec2::new(half_size.x + ARENA_WALL_THICKNESS, half)),
+        (Vec2::new(rect.min.x - half, center.y), Vec2::new(half, half_size.y)),
+        (Vec2::new(rect.max.x + half, center.y), Vec2::new(half, half_size.y)),
+    ];
+
+    for (position, half_extents) in walls {
+        commands.spawn((
+            ArenaWall,
+            RigidBody::Fixed,
+            Collider::cuboid(half_extents.x, half_extents.y),
+            Transform::from_translation(position.extend(1.0)),
+            Name::new("ArenaWall"),
+        ));
+    }
+}
+
+/// Advances the boss to a new phase when its HP crosses a threshold
+fn boss_phase_system(
+    mut commands: Commands,
+    mut hp_events: EventReader<HpChangeEvent>,
+    mut boss_query: Query<(&mut Boss, &mut Stats, &mut AI, &Transform)>,
+    mut camera_focus: ResMut<CameraFocus>,
+    mut phase_events: EventWriter<BossPhaseChangedEvent>,
+    asset_server: Res<AssetServer>,
+    material: Res<ParticleMaterialAsset>,
+) {
+    for event in hp_events.read() {
+        let Ok((mut boss, mut stats, mut ai, transform)) = boss_query.get_mut(event.entity) else {
+            continue;
+        };
+        if event.new_hp <= 0.0 || event.max_hp <= 0.0 {
+            continue;
+        }
+
+        let phase_index = boss.phase_for_ratio(event.new_hp / event.max_hp);
+        if phase_index <= boss.current_phase {
+            continue;
+        }
+
+        boss.current_phase = phase_index;
+        let phase = boss.phases[phase_index].clone();
+        info!(
+            "Boss {:?} entering phase {} '{}' at {:.0}/{:.0} HP",
+            event.entity, phase_index, phase.name, event.new_hp, event.max_hp
+        );
+
+        *ai = AI::new(phase.moves.clone());
+        stats.set_base(StatKind::Speed, phase.speed);
+
+        commands.entity(event.entity).insert((
+            PhaseTransition {
+                timer: Timer::from_seconds(BOSS_PHASE_TRANSITION_TIME, TimerMode::Once),
+            },
+            Invulnerable,
+            AISuspended,
+        ));
+        camera_focus.target = Some(event.entity);
+
+        spawn_floating_text(
+            &mut commands,
+            FloatingTextConfig {
+                text: phase.name.to_uppercase(),
+                color: Color::srgb(1.0, 0.5, 0.1), // Orange color
+                position: transform.translation,
+                lifetime: Duration::from_secs_f32(BOSS_PHASE_TRANSITION_TIME),
+                font_size: 40.0,
+                float_distance: 150.0,
+            },
+        );
+        let particle_entity = commands
+            .spawn((
+                ParticleEffectHandle(asset_server.load("berserker_active.ron")),
+                Transform::from_translation(Vec3::ZERO),
+                ParticleSpawner(material.0.clone()),
+                OneShot::Despawn,
+            ))
+            .id();
+        commands.entity(event.entity).add_child(particle_entity);
+
+        phase_events.write(BossPhaseChangedEvent {
+            boss: event.entity,
+            phase: phase_index,
+        });
+    }
+}
+
+fn update_phase_transitions(
+    mut commands: Commands,
+    mut transition_query: Query<(Entity, &mut PhaseTransition)>,
+    mut camera_focus: ResMut<CameraFocus>,
+    time: Res<Time>,
+) {
+    for (entity, mut transition) in transition_query.iter_mut() {
+        transition.timer.tick(time.delta());
+        if !transition.timer.finished() {
+            continue;
+        }
+
+        debug!("Boss {:?} phase transition finished", entity);
+        commands
+            .entity(entity)
+            .remove::<(PhaseTransition, Invulnerable, AISuspended)>();
+        if camera_focus.target == Some(entity) {
+            camera_focus.target = None;
+        }
+    }
+}
+
+/// Ends the fight and opens the arena once the boss is gone
+fn unlock_boss_arena(
+    mut commands: Commands,
+    mut boss_fight: ResMut<BossFight>,
+    mut camera_focus: ResMut<CameraFocus>,
+    mut defeated_events: EventWriter<BossDefeatedEvent>,
+    unit_query: Query<&Unit, With<Boss>>,
+    wall_query: Query<Entity, With<ArenaWall>>,
+) {
+    let Some(boss) = boss_fight.boss else {
+        return;
+    };
+    let defeated = match unit_query.get(boss) {
+        Ok(unit) => unit.is_dead(),
+        Err(_) => true,
+    };
+    if !defeated {
+        return;
+    }
+
+    info!(
+        "Boss {:?} defeated, unlocking arena '{}'",
+        boss,
+        boss_fight.arena.as_deref().unwrap_or_default()
+    );
+    for wall in wall_query.iter() {
+        commands.entity(wall).despawn();
+    }
+    if camera_focus.target == Some(boss) {
+        camera_focus.target = None;
+    }
+    boss_fight.boss = None;
+    boss_fight.arena = None;
+    defeated_events.write(BossDefeatedEvent { boss });
+}
+
+/// Abandons any fight in progress when the player leaves the map
+fn reset_boss_fight_on_level_change(
+    mut commands: Commands,
+    mut transition_events: EventReader<LevelTransitionEvent>,
+    mut boss_fight: ResMut<BossFight>,
+    mut camera_focus: ResMut<CameraFocus>,
+    mut boss_arenas: ResMut<BossArenas>,
+    wall_query: Query<Entity, With<ArenaWall>>,
+) {
+    if transition_events.read().last().is_none() {
+        return;
+    }
+
+    abandon_boss_fight(&mut commands, &mut boss_fight, &mut camera_focus, &wall_query);
+    boss_arenas.arenas.clear();
+}
+
+/// Reopens the arena when the player respawns away from it, so walking back in restarts the
+/// fight and the camera follows the new hero
+fn reset_boss_fight_on_respawn(
+    mut commands: Commands,
+    mut respawned_events: EventReader<PlayerRespawnedEvent>,
+    mut boss_fight: ResMut<BossFight>,
+    mut camera_focus: ResMut<CameraFocus>,
+    wall_query: Query<Entity, With<ArenaWall>>,
+) {
+    if respawned_events.read().last().is_none() {
+        return;
+    }
+    if let Some(arena) = boss_fight.arena.as_deref() {
+        info!("Player respawned, reopening arena '{}'", arena);
+    }
+    abandon_boss_fight(&mut commands, &mut boss_fight, &mut camera_focus, &wall_query);
+}
+
+fn abandon_boss_fight(
+    commands: &mut Commands,
+    boss_fight: &mut BossFight,
+    camera_focus: &mut CameraFocus,
+    wall_query: &Query<Entity, With<ArenaWall>>,
+) {
+    for wall in wall_query.iter() {
+        commands.entity(wall).despawn();
+    }
+    boss_fight.boss = None;
+    boss_fight.arena = None;
+    camera_focus.target = None;
+}
@@ -1,19 +1,35 @@
-use crate::berserker::Berserker;
-use crate::constants::{BERSERKER_FACTOR, CRITICAL_EXPOSE, REFLECT, STUN_DURATION}; // Assuming REFLECT is defined in constants
+use crate::constants::{
+    CRITICAL_EXPOSE, GUARD_DAMAGE_FACTOR, REFLECT, STUN_DURATION,
+}; // Assuming REFLECT is defined in constants
 use crate::custom_move::{ExecuteMoveEvent, Move, MoveInput, MovePhase, MoveType, PlayerMove};
-use crate::damage::Damage;
+use crate::damage::{Damage, Resistances};
+use crate::defense::Guarding;
+use crate::force::{Factions, Force};
 use crate::float_text::{spawn_best_range_text, spawn_critical_hit_text};
 use crate::global_entity_map::GlobalEntityMap;
 use crate::particle::ParticleMaterialAsset;
 use crate::physics::*;
+use crate::stats::{StatKind, Stats};
 use crate::stun::Stun;
-use crate::unit::{HpChangeEvent, Unit};
+use crate::unit::{HpChangeEvent, Invulnerable, Unit};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy_enoki::prelude::*;
 use bevy_rapier2d::prelude::*;
 use rand::random;
 use std::collections::HashSet;
 
+/// Attacker and target state that changes how a hit lands, grouped to stay under the system param limit
+#[derive(SystemParam)]
+pub struct HitModifiers<'w, 's> {
+    stats_query: Query<'w, 's, &'static Stats>,
+    guard_query: Query<'w, 's, &'static Guarding>,
+    invulnerable_query: Query<'w, 's, (), With<Invulnerable>>,
+    force_query: Query<'w, 's, &'static Force>,
+    factions: Res<'w, Factions>,
+    resistance_query: Query<'w, 's, &'static Resistances>,
+}
+
 pub fn handle_collisions(
     mut collision_events: EventReader<CollisionEvent>,
     damage_query: Query<&Damage>,
@@ -29,7 +45,7 @@ pub fn handle_collisions(
     material: Res<ParticleMaterialAsset>,
     global_entities: Res<GlobalEntityMap>,
     mut event_writer: EventWriter<HpChangeEvent>,
-    berserker_query: Query<&Berserker>,
+    hit_modifiers: HitModifiers,
 ) {
     let mut processed_damage_pairs: HashSet<(Entity, Entity)> = HashSet::new();
 
@@ -85,7 +101,7 @@ pub fn handle_collisions(
                         &material,
                         &global_entities,
                         &mut event_writer,
-                        & berserker_query,
+                        &hit_modifiers,
                     );
                     process_hit(
                         *entity2,
@@ -101,7 +117,7 @@ pub fn handle_collisions(
                         &material,
                         &global_entities,
                         &mut event_writer,
-                        & berserker_query,
+                        &hit_modifiers,
                     );
                 }
             }
@@ -189,11 +205,24 @@ fn process_hit(
     material: &Res<ParticleMaterialAsset>,
     global_entities: &Res<GlobalEntityMap>,
     event_writer: &mut EventWriter<HpChangeEvent>,
-    berserker_query: &Query<&Berserker>,
+    hit_modifiers: &HitModifiers,
 ) {
     debug!("process hit");
+    if hit_modifiers.invulnerable_query.get(target).is_ok() {
+        debug!("Target {:?} is invulnerable, ignoring hit", target);
+        return;
+    }
     if let (Ok(damage), Ok(mut tu)) = (damage_query.get(attacker), unit_query.get_mut(target)) {
         debug!("damage components ready");
+        // No friendly fire between allied forces
+        if let (Ok(source_force), Ok(target_force)) =
+            (hit_modifiers.force_query.get(damage.source), hit_modifiers.force_query.get(target))
+        {
+            if hit_modifiers.factions.is_allied(source_force, target_force) {
+                debug!("Ignoring hit on allied entity {:?}", target);
+                return;
+            }
+        }
         if let Ok((enemy_entity, mut enemy_velocity, enemy_transform)) = enemy_query.get_mut(target)
         {
             debug!("enemy components ready");
@@ -252,16 +281,23 @@ fn process_hit(
                     let mut damage_amount = damage.get_amount();
                     let old_hp = tu.hp;
 
-                    // Check for Berserker component on damage source
-                    if let Ok(berserker) = berserker_query.get(damage.source) {
-                        if berserker.level == 1 {
-                            damage_amount *= BERSERKER_FACTOR;
-                            critical_rate *= BERSERKER_FACTOR;
-                            debug!(
-                                "Berserker level 1 bonus applied - damage: {:.1}, critical rate: {:.2}",
-                                damage_amount, critical_rate
-                            );
+                    // Levels, stances and buffs of the attacker, criticals stay off where impossible
+                    if let Ok(stats) = hit_modifiers.stats_query.get(damage.source) {
+                        damage_amount = stats.apply(StatKind::Damage, damage_amount);
+                        if critical_rate > 0.0 {
+                            critical_rate = stats.apply(StatKind::CritRate, critical_rate);
                         }
+                        debug!(
+                            "Attacker stats applied - damage: {:.1}, critical rate: {:.2}",
+                            damage_amount, critical_rate
+                        );
+                    }
+
+                    // A guarding target cannot be critically hit
+                    let is_guarding = hit_modifiers.guard_query.get(target).is_ok();
+                    if is_guarding {
+                        critical_rate = 0.0;
+                        critical_expose_bonus = 0.0;
                     }
 
                     let random_value: f32 = random();
@@ -303,6 +339,15 @@ fn process_hit(
                         final_damage = final_damage * 0.6;
                     }
 
+                    if let Ok(resistances) = hit_modifiers.resistance_query.get(target) {
+                        final_damage *= resistances.factor(damage.get_type());
+                    }
+
+                    if is_guarding {
+                        debug!("Target {:?} is guarding - damage reduced", target);
+                        final_damage = final_damage * GUARD_DAMAGE_FACTOR;
+                    }
+
                     if let Some(&attacker_entity) =
                         global_entities.weapon_player.get(&weapon_entity)
                     {
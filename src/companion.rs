@@ -0,0 +1,227 @@
+use crate::ai::{AI, AISuspended, TargetDetector};
+use crate::constants::*;
+use crate::custom_move::PlayerMove;
+use crate::force::{Factions, Force};
+use crate::game_state::GameplaySet;
+use crate::stun::Stun;
+use crate::unit::{HpChangeEvent, HpChangeType, Unit};
+use crate::Player;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::Velocity;
+
+/// Standing order given to companions by the player
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompanionOrder {
+    /// Stay near the player and join fights the player starts
+    Follow,
+    /// Stay put and only fight what comes into reach
+    Hold,
+    /// Go after the player's current target
+    AttackTarget,
+}
+
+/// Allied unit that fights alongside the player
+#[derive(Component)]
+pub struct Companion {
+    pub order: CompanionOrder,
+    pub leash_distance: f32,
+    pub hold_position: Vec2,
+}
+
+impl Companion {
+    pub fn new(position: Vec2) -> Self {
+        Self {
+            order: CompanionOrder::Follow,
+            leash_distance: COMPANION_LEASH_DISTANCE,
+            hold_position: position,
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct CompanionCommandEvent {
+    pub order: CompanionOrder,
+}
+
+pub struct CompanionPlugin;
+
+impl Plugin for CompanionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<CompanionCommandEvent>()
+            .add_systems(Update, handle_companion_commands.in_set(GameplaySet));
+    }
+}
+
+fn handle_companion_commands(
+    mut command_events: EventReader<CompanionCommandEvent>,
+    mut companion_query: Query<(&mut Companion, &mut TargetDetector, &Transform), Without<Player>>,
+    player_query: Query<&TargetDetector, With<Player>>,
+) {
+    for event in command_events.read() {
+        let player_target = player_query
+            .single()
+            .map(|detector| detector.target)
+            .unwrap_or(Entity::PLACEHOLDER);
+
+        for (mut companion, mut detector, transform) in companion_query.iter_mut() {
+            info!("Companion order changed: {:?} -> {:?}", companion.order, event.order);
+            companion.order = event.order;
+            match event.order {
+                CompanionOrder::Hold => {
+                    companion.hold_position = transform.translation.xy();
+                    detector.target = Entity::PLACEHOLDER;
+                }
+                CompanionOrder::AttackTarget => {
+                    if player_target != Entity::PLACEHOLDER {
+                        detector.target = player_target;
+                    }
+                }
+                CompanionOrder::Follow => {}
+            }
+        }
+    }
+}
+
+/// Applies orders and leash rules on top of the regular target detection
+pub fn companion_target_system(
+    mut hp_events: EventReader<HpChangeEvent>,
+    mut companion_query: Query<(&Companion, &mut TargetDetector, &Transform), Without<Player>>,
+    player_query: Query<(Entity, &Transform, &Force), With<Player>>,
+    target_query: Query<(&Transform, &Force), Without<Player>>,
+    factions: Res<Factions>,
+) {
+    let Ok((player_entity, player_transform, player_force)) = player_query.single() else {
+        hp_events.clear();
+        return;
+    };
+
+    // Whatever hostile unit the player just hit becomes the companions' target
+    let mut player_victim = None;
+    for event in hp_events.read() {
+        if event.source != player_entity || !matches!(event.change_type, HpChangeType::Damage) {
+            continue;
+        }
+        if let Ok((_, victim_force)) = target_query.get(event.entity) {
+            if factions.is_hostile(player_force, victim_force) {
+                player_victim = Some(event.entity);
+            }
+        }
+    }
+
+    for (companion, mut detector, transform) in companion_query.iter_mut() {
+        if let Some(victim) = player_victim {
+            if companion.order != CompanionOrder::Hold {
+                detector.target = victim;
+            }
+        }
+
+        if detector.target == Entity::PLACEHOLDER {
+            continue;
+        }
+        let Ok((target_transform, _)) = target_query.get(detector.target) else {
+            continue;
+        };
+
+        let drop_target = match companion.order {
+            // Holding companions only fight what is within reach
+            CompanionOrder::Hold => {
+                transform.translation.distance(target_transform.translation) > COMPANION_HOLD_RANGE
+            }
+            // Never get dragged too far away from the player
+            CompanionOrder::Follow | CompanionOrder::AttackTarget => {
+                transform.translation.distance(player_transform.translation)
+                    > COMPANION_LEASH_BREAK_DISTANCE
+            }
+        };
+        if drop_target {
+            debug!("Companion dropping target {:?}", detector.target);
+            detector.target = Entity::PLACEHOLDER;
+        }
+    }
+}
+
+/// Moves companions without a target back to the player or their hold position
+pub fn companion_follow_system(
+    mut companion_query: Query<
+        (
+            Entity,
+            &Companion,
+            &TargetDetector,
+            &Transform,
+            &mut Velocity,
+            &Unit,
+        ),
+        (With<AI>, Without<Player>, Without<AISuspended>),
+    >,
+    player_query: Query<&Transform, With<Player>>,
+    stun_query: Query<&Stun>,
+    time: Res<Time>,
+) {
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+    let player_position = player_transform.translation.xy();
+
+    for (entity, companion, detector, transform, mut velocity, unit) in companion_query.iter_mut() {
+        if detector.target != Entity::PLACEHOLDER || stun_query.get(entity).is_ok() {
+            continue;
+        }
+
+        let position = transform.translation.xy();
+        let destination = match companion.order {
+            CompanionOrder::Hold => companion.hold_position,
+            CompanionOrder::Follow | CompanionOrder::AttackTarget => {
+                if position.distance(player_position) <= companion.leash_distance {
+                    continue;
+                }
+                // Trail behind the player rather than walking into them
+                let behind = -(player_transform.rotation * Vec3::Y).xy();
+                player_position + behind * companion.leash_distance * 0.5
+            }
+        };
+
+        let offset = destination - position;
+        if offset.length() <= WAYPOINT_REACHED_RANGE {
+            continue;
+        }
+        // Don't overshoot the destination in the last frame
+        let speed = unit
+            .speed
+            .min(offset.length() / time.delta_secs().max(f32::EPSILON));
+        velocity.linvel = offset.normalize() * speed;
+    }
+}
+
+/// Steps companions out of the player's swing arc while the player is attacking
+pub fn companion_spacing_system(
+    mut companion_query: Query<
+        (&Transform, &mut Velocity, &Unit),
+        (With<Companion>, Without<Player>),
+    >,
+    player_query: Query<&Transform, (With<Player>, With<PlayerMove>)>,
+) {
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+    let player_position = player_transform.translation.xy();
+    let forward = (player_transform.rotation * Vec3::Y).xy();
+
+    for (transform, mut velocity, unit) in companion_query.iter_mut() {
+        let offset = transform.translation.xy() - player_position;
+        let distance = offset.length();
+        if distance > COMPANION_SWING_CLEARANCE || distance == 0.0 {
+            continue;
+        }
+        if forward.dot(offset / distance) < COMPANION_SWING_ARC_COS {
+            continue;
+        }
+
+        // Sidestep towards whichever side of the swing we are already on
+        let side = if forward.perp().dot(offset) >= 0.0 {
+            forward.perp()
+        } else {
+            -forward.perp()
+        };
+        velocity.linvel = side * unit.speed;
+    }
+}
@@ -13,6 +13,7 @@ pub const ATTACK_SPEED_FACTOR: f32 = 0.8;
 pub const WORLD_COLOR: Color = Color::srgb(0.2, 0.2, 0.35);
 pub const PLAYER_COLOR: Color = Color::srgb(6.25, 9.4, 9.1);
 pub const ENEMY_COLOR: Color = Color::srgb_u8(109, 119, 129);
+pub const BOSS_COLOR: Color = Color::srgb_u8(150, 40, 40);
 
 pub const MESH_RADIUS: f32 = 25.0;
 
@@ -43,11 +44,57 @@ pub const ACTION_SPACE: u32 = 4;
 
 pub const FORCE_PLAYER: u32 = 0;
 pub const FORCE_ENEMY: u32 = 1;
+pub const FORCE_NEUTRAL: u32 = 2;
+
+// Unit types
+pub const UNIT_TYPE_HERO: &str = "hero";
+pub const UNIT_TYPE_SWORDMAN: &str = "swordman";
+pub const UNIT_TYPE_BOSS: &str = "boss";
+pub const UNIT_TYPE_DUMMY: &str = "dummy";
+/// Folder holding the unit templates, `<id>.unit.ron`
+pub const UNIT_TEMPLATE_DIR: &str = "units";
+/// Folder holding the loot tables, `<id>.loot.ron`
+pub const LOOT_TABLE_DIR: &str = "loot";
 
 pub const ALERT_RANGE: f32 = 1000.0;
 pub const DIS_ALERT_RANGE: f32 = 2000.0;
 pub const STOP_CHASING_RANGE: f32 = 200.0;
 
+// Idle behaviors
+pub const PATROL_LAYER: &str = "Patrol";
+pub const IDLE_SPEED_FACTOR: f32 = 0.5;
+pub const WAYPOINT_REACHED_RANGE: f32 = 10.0;
+pub const DEFAULT_WANDER_RADIUS: f32 = 200.0;
+pub const IDLE_WAIT_TIME: f32 = 2.0;
+
+// Squad tactics
+pub const SQUAD_RING_RADIUS: f32 = 350.0;
+/// Seconds a member may hold an attack token before it is taken back
+pub const ATTACK_TOKEN_TIMEOUT: f32 = 3.0;
+
+// Defensive reactions
+pub const DEFENSE_THREAT_RANGE: f32 = 350.0;
+pub const DEFENSE_COOLDOWN: f32 = 1.5;
+pub const SIDESTEP_IMPULSE_FORCE: f32 = 700.0;
+pub const GUARD_DURATION: f32 = 0.6;
+pub const GUARD_DAMAGE_FACTOR: f32 = 0.3;
+
+// Boss encounters
+pub const BOSS_ARENA_LAYER: &str = "BossArena";
+// Keeps the boss in its arena until the player walks in
+pub const BOSS_ALERT_RANGE: f32 = 400.0;
+pub const BOSS_PHASE_TRANSITION_TIME: f32 = 2.0;
+pub const ARENA_WALL_THICKNESS: f32 = 32.0;
+
+// Companions
+pub const COMPANION_COLOR: Color = Color::srgb(0.4, 0.9, 0.5);
+pub const COMPANION_ALERT_RANGE: f32 = 500.0;
+pub const COMPANION_LEASH_DISTANCE: f32 = 250.0;
+pub const COMPANION_LEASH_BREAK_DISTANCE: f32 = 1200.0;
+pub const COMPANION_HOLD_RANGE: f32 = STOP_CHASING_RANGE + 40.0;
+pub const COMPANION_SWING_CLEARANCE: f32 = 300.0;
+pub const COMPANION_SWING_ARC_COS: f32 = 0.5; // cos(60 degrees)
+
 pub const SPRINT_IMPULSE_FORCE: f32 = 800.0;
 
 pub const BASE_CRITICAL_RATE: f32 = 0.2;
@@ -61,3 +108,47 @@ pub const BERSERKER_FACTOR: f32 = 1.2;
 
 // Stun effect duration when critical hit is dealt
 pub const STUN_DURATION: f32 = 1.0;
+
+// Levels
+pub const START_LEVEL: &str = "map/level.tmx";
+pub const ENTRY_POINT_LAYER: &str = "EntryPoint";
+pub const DEFAULT_ENTRY_POINT: &str = "Default";
+pub const DOOR_ARRIVAL_COOLDOWN: f32 = 1.0;
+
+// Chunk streaming, in chunks around the camera
+pub const CHUNK_LOAD_RADIUS: i32 = 2;
+pub const CHUNK_UNLOAD_RADIUS: i32 = 3;
+
+// Z offset between tilesets drawn in the same tile layer
+pub const TILESET_Z_STEP: f32 = 0.01;
+
+// Wave spawner
+pub const WAVE_SPAWNER_LAYER: &str = "Spawner";
+pub const SCORE_PER_KILL: u32 = 10;
+pub const SCORE_PER_WAVE: u32 = 50;
+
+// Seconds a dead unit shrinks away before it is despawned
+pub const UNIT_DEATH_DURATION: f32 = 0.6;
+
+// Experience, XP to the next level is XP_BASE * level ^ XP_EXPONENT
+pub const MAX_LEVEL: u32 = 20;
+pub const XP_BASE: f32 = 100.0;
+pub const XP_EXPONENT: f32 = 1.5;
+
+// Player death and respawn
+pub const CHECKPOINT_LAYER: &str = "Checkpoint";
+pub const PLAYER_LIVES: u32 = 3;
+pub const PLAYER_CONTINUES: u32 = 2;
+pub const RESPAWN_HP_FRACTION: f32 = 0.5;
+pub const PLAYER_DEATH_DURATION: f32 = 1.5;
+
+// Save files, in the user data directory
+pub const SAVE_DIR_NAME: &str = "character_2d";
+pub const SAVE_FILE_NAME: &str = "save.ron";
+
+// Item pickups dropped as loot
+pub const PICKUP_RADIUS: f32 = 8.0;
+pub const PICKUP_Z: f32 = 0.5;
+pub const PICKUP_SCATTER: f32 = 40.0;
+pub const PICKUP_MAGNET_RADIUS: f32 = 150.0;
+pub const PICKUP_MAGNET_SPEED: f32 = 600.0;
@@ -1,5 +1,6 @@
 use crate::animation_base::*;
 use crate::constants::DURATION_FACTOR;
+use crate::game_state::GameplaySet;
 use crate::global_entity_map::GlobalEntityMap;
 use crate::move_database::*;
 use crate::physics::WeaponKnockback;
@@ -132,6 +133,13 @@ pub struct MoveMetadata {
     pub move_speed: f32,
 }
 
+#[derive(Event)]
+pub struct MoveStartupEvent {
+    pub actor: Entity,
+    pub move_name: String,
+    pub move_type: MoveType,
+}
+
 #[derive(Event)]
 pub struct MoveActiveEvent {
     pub actor: Entity,
@@ -150,10 +158,11 @@ impl Plugin for MovePlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<MoveDatabase>()
             .add_event::<ExecuteMoveEvent>()
+            .add_event::<MoveStartupEvent>()
             .add_event::<MoveActiveEvent>()
             .add_event::<MoveRecoveryEvent>()
-            .add_systems(Update, handle_move_execution)
-            .add_systems(FixedUpdate, update_moves);
+            .add_systems(Update, handle_move_execution.in_set(GameplaySet))
+            .add_systems(FixedUpdate, update_moves.in_set(GameplaySet));
     }
 }
 
@@ -165,6 +174,7 @@ fn handle_move_execution(
     global_entity_map: Res<GlobalEntityMap>,
     mut weapon_knockback_query: Query<&mut WeaponKnockback>,
     mut end_move_events: EventWriter<MoveRecoveryEvent>,
+    mut startup_events: EventWriter<MoveStartupEvent>,
 ) {
     for event in move_events.read() {
         if let Ok((entity, current_move)) = query.get_mut(event.entity) {
@@ -194,6 +204,7 @@ fn handle_move_execution(
                     entity,
                     &global_entity_map,
                     &mut weapon_knockback_query,
+                    &mut startup_events,
                 );
                 continue;
             }
@@ -212,6 +223,7 @@ fn handle_move_execution(
                 entity,
                 &global_entity_map,
                 &mut weapon_knockback_query,
+                &mut startup_events,
             );
         }
     }
@@ -224,6 +236,7 @@ fn force_start_move(
     entity: Entity,
     global_entity_map: &GlobalEntityMap,
     weapon_knockback_query: &mut Query<&mut WeaponKnockback>,
+    startup_events: &mut EventWriter<MoveStartupEvent>,
 ) {
     if let Some(move_data) = move_db.moves.get(&event.move_name) {
         if let Some(actor) = global_entity_map.weapon_player.get(&event.entity) {
@@ -233,6 +246,11 @@ fn force_start_move(
             commands.entity(*actor).insert(PlayerMove {
                 move_metadata: move_data.clone(),
             });
+            startup_events.write(MoveStartupEvent {
+                actor: *actor,
+                move_name: move_data.name.clone(),
+                move_type: move_data.move_type.clone(),
+            });
 
             debug!(
                 "Force-started interrupt move '{}' for entity {:?}, overriding any existing move",
@@ -278,6 +296,7 @@ fn start_new_move(
     entity: Entity,
     global_entity_map: &GlobalEntityMap,
     mut weapon_knockback_query: &mut Query<&mut WeaponKnockback>,
+    startup_events: &mut EventWriter<MoveStartupEvent>,
 ) {
     if let Some(move_data) = move_db.moves.get(&event.move_name) {
         if let Some(actor) = global_entity_map.weapon_player.get(&event.entity) {
@@ -286,6 +305,11 @@ fn start_new_move(
             commands.entity(*actor).insert(PlayerMove {
                 move_metadata: move_data.clone(),
             });
+            startup_events.write(MoveStartupEvent {
+                actor: *actor,
+                move_name: move_data.name.clone(),
+                move_type: move_data.move_type.clone(),
+            });
 
             // Update knockback using the extracted method
             update_knockback(entity, move_data, global_entity_map, weapon_knockback_query);
@@ -338,6 +362,7 @@ fn update_moves(
     mut player_query: Query<Entity, With<crate::Player>>,
     mut start_move_events: EventWriter<MoveActiveEvent>,
     mut end_move_events: EventWriter<MoveRecoveryEvent>,
+    mut startup_events: EventWriter<MoveStartupEvent>,
     animation_db: Res<AnimationDatabase>,
     time: Res<Time>,
     global_entity_map: Res<GlobalEntityMap>,
@@ -395,6 +420,11 @@ fn update_moves(
             
             // FIX: Update PlayerMove component on the player entity
             if let Some(&player_entity) = global_entity_map.weapon_player.get(&entity) {
+                startup_events.write(MoveStartupEvent {
+                    actor: player_entity,
+                    move_name: next_move_data_clone.name.clone(),
+                    move_type: next_move_data_clone.move_type.clone(),
+                });
                 commands.entity(player_entity).insert(PlayerMove {
                     move_metadata: next_move_data_clone,
                 });
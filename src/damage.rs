@@ -1,4 +1,6 @@
 use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
 
 /// Component that indicates an entity can deal damage
 #[derive(Component, Debug, Clone)]
@@ -45,7 +47,7 @@ impl Damage {
 }
 
 /// Types of damage that can be dealt
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
 pub enum DamageType {
     Physical,
     Magical,
@@ -72,3 +74,15 @@ impl DamageType {
         }
     }
 }
+
+/// Fraction of incoming damage ignored per damage type; 0.5 halves it, negative
+/// values make the unit weak to that type
+#[derive(Component, Debug, Clone, Default, Deserialize)]
+pub struct Resistances(pub HashMap<DamageType, f32>);
+
+impl Resistances {
+    /// Multiplier applied to damage of the given type
+    pub fn factor(&self, damage_type: DamageType) -> f32 {
+        1.0 - self.0.get(&damage_type).copied().unwrap_or(0.0)
+    }
+}
@@ -0,0 +1,233 @@
+use crate::ai::{AI, AISuspended, TargetDetector};
+use crate::constants::*;
+use crate::custom_move::{ExecuteMoveEvent, MoveInput, MoveStartupEvent, MoveType, PlayerMove};
+use crate::difficulty::Difficulty;
+use crate::float_text::spawn_guard_text;
+use crate::game_state::GameplaySet;
+use crate::global_entity_map::GlobalEntityMap;
+use crate::move_database::MoveDatabase;
+use crate::physics::apply_impulse;
+use crate::stun::Stun;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::Velocity;
+use rand::Rng;
+
+/// Ways an AI can answer an incoming attack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Counter {
+    /// Dash sideways out of the attack
+    Sidestep,
+    /// Brace and take reduced damage
+    Guard,
+    /// Thrust with `SwordStub` so a Swing collides with it and triggers `REFLECT`
+    Reflect,
+}
+
+/// Lets an AI read opponents' move startups and respond with one of its counters
+#[derive(Component)]
+pub struct DefensiveReaction {
+    pub counters: Vec<Counter>,
+    pub cooldown: Timer,
+}
+
+impl Default for DefensiveReaction {
+    fn default() -> Self {
+        let mut cooldown = Timer::from_seconds(DEFENSE_COOLDOWN, TimerMode::Once);
+        // Start ready to react
+        cooldown.tick(cooldown.duration());
+        Self {
+            counters: vec![Counter::Sidestep, Counter::Guard, Counter::Reflect],
+            cooldown,
+        }
+    }
+}
+
+/// A counter that has been decided on and fires once the reaction delay elapses
+#[derive(Component)]
+pub struct PendingCounter {
+    pub counter: Counter,
+    pub attacker: Entity,
+    pub timer: Timer,
+}
+
+/// Damage taken while this is present is reduced and cannot be critical
+#[derive(Component)]
+pub struct Guarding {
+    pub timer: Timer,
+}
+
+pub struct DefensePlugin;
+
+impl Plugin for DefensePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (read_incoming_attacks, execute_pending_counters, update_guarding)
+                .chain()
+                .in_set(GameplaySet),
+        );
+    }
+}
+
+fn read_incoming_attacks(
+    mut commands: Commands,
+    mut startup_events: EventReader<MoveStartupEvent>,
+    mut defender_query: Query<
+        (Entity, &TargetDetector, &Transform, &mut DefensiveReaction),
+        (With<AI>, Without<PendingCounter>, Without<AISuspended>),
+    >,
+    transform_query: Query<&Transform>,
+    busy_query: Query<(), Or<(With<PlayerMove>, With<Stun>)>>,
+    move_db: Res<MoveDatabase>,
+    difficulty: Res<Difficulty>,
+    time: Res<Time>,
+) {
+    for (_, _, _, mut reaction) in defender_query.iter_mut() {
+        reaction.cooldown.tick(time.delta());
+    }
+
+    let settings = difficulty.settings();
+    let mut rng = rand::thread_rng();
+
+    for event in startup_events.read() {
+        // Interrupts such as REFLECT are reactions themselves, not attacks to read
+        if event.move_type == MoveType::Interrupt {
+            continue;
+        }
+        let Ok(attacker_transform) = transform_query.get(event.actor) else {
+            continue;
+        };
+
+        for (entity, detector, transform, mut reaction) in defender_query.iter_mut() {
+            if detector.target != event.actor || entity == event.actor {
+                continue;
+            }
+            if !reaction.cooldown.finished() || busy_query.get(entity).is_ok() {
+                continue;
+            }
+            if transform.translation.distance(attacker_transform.translation)
+                > DEFENSE_THREAT_RANGE
+            {
+                continue;
+            }
+
+            // Roll once per incoming attack; a failed read still costs the cooldown
+            reaction.cooldown.reset();
+            if rng.r#gen::<f32>() >= settings.reaction_chance {
+                trace!(
+                    "Entity {:?} failed to read '{}' from {:?}",
+                    entity, event.move_name, event.actor
+                );
+                continue;
+            }
+
+            // Reflect only works if our stub becomes active before the swing ends
+            let reflect_in_time = event.move_type == MoveType::Swing
+                && match (
+                    move_db.moves.get(&event.move_name),
+                    move_db.moves.get(SWORD_STUB),
+                ) {
+                    (Some(incoming), Some(stub)) => {
+                        settings.reaction_delay + stub.startup_time
+                            < incoming.startup_time + incoming.active_time
+                    }
+                    _ => false,
+                };
+
+            let options: Vec<Counter> = reaction
+                .counters
+                .iter()
+                .copied()
+                .filter(|counter| *counter != Counter::Reflect || reflect_in_time)
+                .collect();
+            if options.is_empty() {
+                continue;
+            }
+
+            // Prefer the reflect when it is possible, it is the only counter that punishes
+            let counter = if options.contains(&Counter::Reflect) {
+                Counter::Reflect
+            } else {
+                options[rng.gen_range(0..options.len())]
+            };
+
+            debug!(
+                "Entity {:?} read '{}' from {:?}, answering with {:?} in {:.2}s",
+                entity, event.move_name, event.actor, counter, settings.reaction_delay
+            );
+            commands.entity(entity).insert(PendingCounter {
+                counter,
+                attacker: event.actor,
+                timer: Timer::from_seconds(settings.reaction_delay, TimerMode::Once),
+            });
+        }
+    }
+}
+
+fn execute_pending_counters(
+    mut commands: Commands,
+    mut pending_query: Query<(Entity, &mut PendingCounter, &Transform, &mut Velocity)>,
+    transform_query: Query<&Transform>,
+    stun_query: Query<&Stun>,
+    global_entities: Res<GlobalEntityMap>,
+    mut move_events: EventWriter<ExecuteMoveEvent>,
+    time: Res<Time>,
+) {
+    for (entity, mut pending, transform, mut velocity) in pending_query.iter_mut() {
+        pending.timer.tick(time.delta());
+        if !pending.timer.finished() {
+            continue;
+        }
+        commands.entity(entity).remove::<PendingCounter>();
+
+        // Getting stunned during the reaction delay cancels the counter
+        if stun_query.get(entity).is_ok() {
+            continue;
+        }
+
+        match pending.counter {
+            Counter::Sidestep => {
+                if let Ok(attacker_transform) = transform_query.get(pending.attacker) {
+                    let to_attacker = (attacker_transform.translation - transform.translation)
+                        .xy()
+                        .normalize_or_zero();
+                    let side = if rand::random::<bool>() { 1.0 } else { -1.0 };
+                    apply_impulse(
+                        entity,
+                        to_attacker.perp() * side,
+                        SIDESTEP_IMPULSE_FORCE,
+                        &mut velocity,
+                    );
+                }
+            }
+            Counter::Guard => {
+                commands.entity(entity).insert(Guarding {
+                    timer: Timer::from_seconds(GUARD_DURATION, TimerMode::Once),
+                });
+                spawn_guard_text(&mut commands, transform.translation);
+            }
+            Counter::Reflect => {
+                if let Some(weapon) = global_entities.player_weapon.get(&entity) {
+                    move_events.write(ExecuteMoveEvent {
+                        entity: *weapon,
+                        move_name: SWORD_STUB.to_string(),
+                        move_input: MoveInput::Attack,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn update_guarding(
+    mut commands: Commands,
+    mut guard_query: Query<(Entity, &mut Guarding)>,
+    time: Res<Time>,
+) {
+    for (entity, mut guarding) in guard_query.iter_mut() {
+        guarding.timer.tick(time.delta());
+        if guarding.timer.finished() {
+            commands.entity(entity).remove::<Guarding>();
+        }
+    }
+}
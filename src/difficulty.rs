@@ -0,0 +1,49 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Global difficulty selection that AI tuning is read from
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+/// AI tuning values for a difficulty level
+#[derive(Debug, Clone, Copy)]
+pub struct DifficultySettings {
+    /// How many enemies may attack the same target at once
+    pub attack_tokens: usize,
+    /// Seconds between seeing an attack start up and answering it
+    pub reaction_delay: f32,
+    /// Chance (0.0 to 1.0) that an incoming attack is read at all
+    pub reaction_chance: f32,
+    /// Multiplier on the number of units in each spawner wave
+    pub wave_size: f32,
+}
+
+impl Difficulty {
+    pub fn settings(&self) -> DifficultySettings {
+        match self {
+            Difficulty::Easy => DifficultySettings {
+                attack_tokens: 1,
+                reaction_delay: 0.35,
+                reaction_chance: 0.2,
+                wave_size: 0.75,
+            },
+            Difficulty::Normal => DifficultySettings {
+                attack_tokens: 2,
+                reaction_delay: 0.2,
+                reaction_chance: 0.45,
+                wave_size: 1.0,
+            },
+            Difficulty::Hard => DifficultySettings {
+                attack_tokens: 3,
+                reaction_delay: 0.1,
+                reaction_chance: 0.75,
+                wave_size: 1.25,
+            },
+        }
+    }
+}
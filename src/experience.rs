@@ -0,0 +1,239 @@
+use crate::constants::*;
+use crate::float_text::{FloatingTextConfig, spawn_floating_text};
+use crate::game_state::GameplaySet;
+use crate::stats::{ModifierSource, StatKind, StatModifier, Stats};
+use crate::unit::{HpChangeEvent, HpChangeType, Unit};
+use crate::unit_death::UnitDiedEvent;
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// How a stat grows with level, as a bonus over its level 1 value
+#[derive(Debug, Clone, Copy)]
+pub enum GrowthCurve {
+    /// Adds `per_level` for every level above 1
+    Linear { per_level: f32 },
+    /// Compounds by `rate` for every level above 1, e.g. 1.05 is 5% per level
+    Exponential { rate: f32 },
+}
+
+impl GrowthCurve {
+    pub fn bonus(&self, level: u32) -> f32 {
+        let levels = level.saturating_sub(1);
+        match self {
+            GrowthCurve::Linear { per_level } => per_level * levels as f32,
+            GrowthCurve::Exponential { rate } => rate.powi(levels as i32) - 1.0,
+        }
+    }
+}
+
+/// XP thresholds and stat growth
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct LevelingConfig {
+    pub max_level: u32,
+    /// XP from level 1 to 2; later levels need `xp_base * level ^ xp_exponent`
+    pub xp_base: f32,
+    pub xp_exponent: f32,
+    /// Percent-add modifier on max HP
+    pub max_hp: GrowthCurve,
+    /// Percent-add modifier on weapon damage
+    pub damage: GrowthCurve,
+    /// Flat modifier on the critical rate of every move
+    pub crit_rate: GrowthCurve,
+    /// Percent-add modifier on speed
+    pub speed: GrowthCurve,
+}
+
+impl Default for LevelingConfig {
+    fn default() -> Self {
+        Self {
+            max_level: MAX_LEVEL,
+            xp_base: XP_BASE,
+            xp_exponent: XP_EXPONENT,
+            max_hp: GrowthCurve::Linear { per_level: 0.1 },
+            damage: GrowthCurve::Exponential { rate: 1.06 },
+            crit_rate: GrowthCurve::Linear { per_level: 0.01 },
+            speed: GrowthCurve::Linear { per_level: 0.02 },
+        }
+    }
+}
+
+impl LevelingConfig {
+    /// XP needed to go from `level` to the next one
+    pub fn xp_to_next(&self, level: u32) -> u32 {
+        (self.xp_base * (level as f32).powf(self.xp_exponent)).round() as u32
+    }
+}
+
+/// Units with this component level up from the kills they make
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Experience {
+    pub level: u32,
+    /// XP towards the next level
+    pub xp: u32,
+}
+
+impl Default for Experience {
+    fn default() -> Self {
+        Self { level: 1, xp: 0 }
+    }
+}
+
+impl Experience {
+    /// Adds XP, levelling up as many times as it covers. Returns the levels gained.
+    pub fn gain(&mut self, xp: u32, config: &LevelingConfig) -> u32 {
+        let start = self.level;
+        self.xp += xp;
+        while self.level < config.max_level && self.xp >= config.xp_to_next(self.level) {
+            self.xp -= config.xp_to_next(self.level);
+            self.level += 1;
+        }
+        if self.level == config.max_level {
+            self.xp = 0;
+        }
+        self.level - start
+    }
+}
+
+/// XP granted to whoever kills the unit
+#[derive(Component, Debug, Clone, Copy)]
+pub struct XpReward(pub u32);
+
+#[derive(Event, Debug, Clone)]
+pub struct LevelUpEvent {
+    pub entity: Entity,
+    pub level: u32,
+}
+
+pub struct ExperiencePlugin;
+
+impl Plugin for ExperiencePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelingConfig>()
+            .add_event::<LevelUpEvent>()
+            .add_systems(
+                Update,
+                (grant_xp, refresh_level_modifiers, celebrate_level_up)
+                    .chain()
+                    .in_set(GameplaySet),
+            );
+    }
+}
+
+fn grant_xp(
+    mut died_events: EventReader<UnitDiedEvent>,
+    reward_query: Query<&XpReward>,
+    mut experience_query: Query<&mut Experience>,
+    config: Res<LevelingConfig>,
+    mut level_up_events: EventWriter<LevelUpEvent>,
+) {
+    for event in died_events.read() {
+        let Ok(reward) = reward_query.get(event.entity) else {
+            continue;
+        };
+        let Ok(mut experience) = experience_query.get_mut(event.killer) else {
+            continue;
+        };
+        let gained = experience.gain(reward.0, &config);
+        debug!(
+            "{:?} gained {} XP, level {} ({} XP)",
+            event.killer, reward.0, experience.level, experience.xp
+        );
+        if gained > 0 {
+            level_up_events.write(LevelUpEvent {
+                entity: event.killer,
+                level: experience.level,
+            });
+        }
+    }
+}
+
+/// Replaces the level modifiers whenever the level changes, or once the unit's stats exist
+fn refresh_level_modifiers(
+    mut stats_query: Query<(&Experience, &mut Stats), Or<(Changed<Experience>, Added<Stats>)>>,
+    config: Res<LevelingConfig>,
+) {
+    for (experience, mut stats) in stats_query.iter_mut() {
+        let level = experience.level;
+        let source = ModifierSource::Level;
+        stats.set_source(
+            &source,
+            [
+                StatModifier::percent_add(StatKind::MaxHp, config.max_hp.bonus(level), source.clone()),
+                StatModifier::percent_add(StatKind::Damage, config.damage.bonus(level), source.clone()),
+                StatModifier::flat(StatKind::CritRate, config.crit_rate.bonus(level), source.clone()),
+                StatModifier::percent_add(StatKind::Speed, config.speed.bonus(level), source.clone()),
+            ],
+        );
+    }
+}
+
+/// Heals to the new max HP and announces the level
+fn celebrate_level_up(
+    mut commands: Commands,
+    mut level_up_events: EventReader<LevelUpEvent>,
+    mut unit_query: Query<(&mut Unit, &Stats, &Transform)>,
+    mut hp_events: EventWriter<HpChangeEvent>,
+) {
+    for event in level_up_events.read() {
+        let Ok((mut unit, stats, transform)) = unit_query.get_mut(event.entity) else {
+            continue;
+        };
+        info!("{} reached level {}", unit.name, event.level);
+        let old_hp = unit.hp;
+        // Read straight from the stats, the new level modifiers aren't synced onto the unit yet
+        unit.max_hp = stats.value(StatKind::MaxHp);
+        unit.hp = unit.max_hp;
+        hp_events.write(HpChangeEvent {
+            entity: event.entity,
+            source: event.entity,
+            old_hp,
+            new_hp: unit.hp,
+            max_hp: unit.max_hp,
+            change_type: HpChangeType::SetValue,
+        });
+        spawn_floating_text(
+            &mut commands,
+            FloatingTextConfig {
+                text: format!("LEVEL {}", event.level),
+                color: Color::srgb(0.4, 1.0, 0.5),
+                position: transform.translation,
+                lifetime: Duration::from_millis(2000),
+                font_size: 32.0,
+                float_distance: 140.0,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn growth_starts_at_level_one() {
+        let linear = GrowthCurve::Linear { per_level: 0.1 };
+        let exponential = GrowthCurve::Exponential { rate: 2.0 };
+        assert_eq!(linear.bonus(1), 0.0);
+        assert!((linear.bonus(3) - 0.2).abs() < 1e-6);
+        assert_eq!(exponential.bonus(1), 0.0);
+        assert_eq!(exponential.bonus(3), 3.0);
+    }
+
+    #[test]
+    fn gain_crosses_several_levels() {
+        let config = LevelingConfig {
+            max_level: 4,
+            xp_base: 100.0,
+            xp_exponent: 1.0,
+            ..Default::default()
+        };
+        let mut experience = Experience::default();
+        assert_eq!(experience.gain(50, &config), 0);
+        // 100 to reach level 2, then 200 to reach level 3
+        assert_eq!(experience.gain(260, &config), 2);
+        assert_eq!((experience.level, experience.xp), (3, 10));
+        // Capped at the max level
+        assert_eq!(experience.gain(10_000, &config), 1);
+        assert_eq!((experience.level, experience.xp), (4, 0));
+    }
+}
@@ -112,6 +112,20 @@ pub fn spawn_sprint_ready_text(commands: &mut Commands, position: Vec3) -> Entit
     )
 }
 
+pub fn spawn_guard_text(commands: &mut Commands, position: Vec3) -> Entity {
+    spawn_floating_text(
+        commands,
+        FloatingTextConfig {
+            text: "guard".to_string(),
+            color: Color::srgb(0.5, 0.7, 1.0), // Blue color
+            position,
+            lifetime: Duration::from_millis(1000),
+            font_size: 18.0,
+            float_distance: 80.0,
+        },
+    )
+}
+
 /// System to cleanup floating text after their lifetime expires
 pub fn cleanup_floating_text_system(
     mut commands: Commands,
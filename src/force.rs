@@ -1,6 +1,140 @@
 use bevy::prelude::*;
+use std::collections::HashMap;
 
-#[derive(Component)]
+use crate::constants::*;
+use crate::game_state::GameplaySet;
+use crate::unit::{HpChangeEvent, HpChangeType};
+
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Force {
     pub force: u32,
 }
+
+/// How one force treats another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    Hostile,
+    Neutral,
+    Allied,
+}
+
+/// Relationship matrix between forces. Pairs without an entry are allied with
+/// themselves and hostile to everyone else.
+#[derive(Resource)]
+pub struct Factions {
+    relations: HashMap<(u32, u32), Relation>,
+}
+
+#[derive(Event)]
+pub struct RelationChangedEvent {
+    pub a: u32,
+    pub b: u32,
+    pub old_relation: Relation,
+    pub new_relation: Relation,
+}
+
+impl Default for Factions {
+    fn default() -> Self {
+        let mut factions = Self {
+            relations: HashMap::new(),
+        };
+        factions.set_relation(FORCE_PLAYER, FORCE_ENEMY, Relation::Hostile);
+        factions.set_relation(FORCE_NEUTRAL, FORCE_PLAYER, Relation::Neutral);
+        factions.set_relation(FORCE_NEUTRAL, FORCE_ENEMY, Relation::Neutral);
+        factions
+    }
+}
+
+impl Factions {
+    fn key(a: u32, b: u32) -> (u32, u32) {
+        if a <= b { (a, b) } else { (b, a) }
+    }
+
+    pub fn relation(&self, a: u32, b: u32) -> Relation {
+        match self.relations.get(&Self::key(a, b)) {
+            Some(relation) => *relation,
+            None if a == b => Relation::Allied,
+            None => Relation::Hostile,
+        }
+    }
+
+    /// Set the relation both ways, returning the previous one
+    pub fn set_relation(&mut self, a: u32, b: u32, relation: Relation) -> Relation {
+        let old_relation = self.relation(a, b);
+        self.relations.insert(Self::key(a, b), relation);
+        old_relation
+    }
+
+    pub fn is_hostile(&self, a: &Force, b: &Force) -> bool {
+        self.relation(a.force, b.force) == Relation::Hostile
+    }
+
+    pub fn is_allied(&self, a: &Force, b: &Force) -> bool {
+        self.relation(a.force, b.force) == Relation::Allied
+    }
+}
+
+pub struct FactionPlugin;
+
+impl Plugin for FactionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Factions>()
+            .add_event::<RelationChangedEvent>()
+            .add_systems(Update, provoke_neutral_factions.in_set(GameplaySet));
+    }
+}
+
+/// A neutral force turns hostile towards whoever damages one of its units
+fn provoke_neutral_factions(
+    mut hp_events: EventReader<HpChangeEvent>,
+    mut factions: ResMut<Factions>,
+    force_query: Query<&Force>,
+    mut relation_events: EventWriter<RelationChangedEvent>,
+) {
+    for event in hp_events.read() {
+        if !matches!(event.change_type, HpChangeType::Damage) || event.source == event.entity {
+            continue;
+        }
+        let (Ok(victim), Ok(attacker)) = (force_query.get(event.entity), force_query.get(event.source))
+        else {
+            continue;
+        };
+        if factions.relation(victim.force, attacker.force) != Relation::Neutral {
+            continue;
+        }
+
+        info!(
+            "Force {} attacked by force {}, turning hostile",
+            victim.force, attacker.force
+        );
+        let old_relation = factions.set_relation(victim.force, attacker.force, Relation::Hostile);
+        relation_events.write(RelationChangedEvent {
+            a: victim.force,
+            b: attacker.force,
+            old_relation,
+            new_relation: Relation::Hostile,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_relations() {
+        let factions = Factions::default();
+        assert_eq!(factions.relation(FORCE_PLAYER, FORCE_PLAYER), Relation::Allied);
+        assert_eq!(factions.relation(FORCE_PLAYER, FORCE_ENEMY), Relation::Hostile);
+        assert_eq!(factions.relation(FORCE_ENEMY, FORCE_NEUTRAL), Relation::Neutral);
+        assert_eq!(factions.relation(FORCE_PLAYER, 42), Relation::Hostile);
+    }
+
+    #[test]
+    fn test_set_relation_is_symmetric() {
+        let mut factions = Factions::default();
+        let old = factions.set_relation(FORCE_NEUTRAL, FORCE_PLAYER, Relation::Hostile);
+        assert_eq!(old, Relation::Neutral);
+        assert_eq!(factions.relation(FORCE_PLAYER, FORCE_NEUTRAL), Relation::Hostile);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::Player;
+use crate::constants::*;
+use crate::level::level::LevelTransitionEvent;
+use crate::level::tiled::TiledMapLoadState;
+use crate::respawn::PlayerDying;
+use crate::unit_template::PendingUnitSpawns;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::RapierConfiguration;
+
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameState {
+    #[default]
+    MainMenu,
+    /// Waiting for the map to be built and the hero to be spawned
+    Loading,
+    Playing,
+    Paused,
+    GameOver,
+}
+
+/// Gameplay systems, which only run while `GameState::Playing`
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GameplaySet;
+
+/// Full-screen overlay for menu-like states, removed when the state is left
+#[derive(Component)]
+struct StateScreen;
+
+pub struct GameStatePlugin;
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<GameState>()
+            .configure_sets(Update, GameplaySet.run_if(in_state(GameState::Playing)))
+            .configure_sets(FixedUpdate, GameplaySet.run_if(in_state(GameState::Playing)))
+            .add_systems(OnEnter(GameState::MainMenu), spawn_main_menu)
+            .add_systems(OnEnter(GameState::Paused), (pause_game, spawn_pause_screen))
+            .add_systems(OnExit(GameState::Paused), resume_game)
+            .add_systems(OnEnter(GameState::GameOver), spawn_game_over_screen)
+            .add_systems(OnExit(GameState::MainMenu), despawn_state_screen)
+            .add_systems(OnExit(GameState::Paused), despawn_state_screen)
+            .add_systems(OnExit(GameState::GameOver), despawn_state_screen)
+            .add_systems(
+                Update,
+                (
+                    start_game.run_if(in_state(GameState::MainMenu)),
+                    finish_loading.run_if(in_state(GameState::Loading)),
+                    (toggle_pause, detect_game_over)
+                        .run_if(in_state(GameState::Playing).or(in_state(GameState::Paused))),
+                    restart_game.run_if(in_state(GameState::GameOver)),
+                ),
+            );
+    }
+}
+
+fn start_game(keyboard_input: Res<ButtonInput<KeyCode>>, mut next_state: ResMut<NextState<GameState>>) {
+    if keyboard_input.just_pressed(KeyCode::Enter) {
+        info!("Starting game");
+        next_state.set(GameState::Loading);
+    }
+}
+
+/// Starts playing once the map is built and the hero is in it
+fn finish_loading(
+    map_query: Query<&TiledMapLoadState>,
+    player_query: Query<(), With<Player>>,
+    pending: Res<PendingUnitSpawns>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    let map_loaded = map_query.iter().any(|load_state| load_state.load_flag);
+    if map_loaded && !player_query.is_empty() && pending.requests.is_empty() {
+        info!("Level ready");
+        next_state.set(GameState::Playing);
+    }
+}
+
+fn toggle_pause(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Escape) {
+        return;
+    }
+    match state.get() {
+        GameState::Playing => next_state.set(GameState::Paused),
+        GameState::Paused => next_state.set(GameState::Playing),
+        _ => {}
+    }
+}
+
+/// The hero is despawned once its death plays out without lives left to respawn
+fn detect_game_over(
+    player_query: Query<(), Or<(With<Player>, With<PlayerDying>)>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if *state.get() == GameState::Playing && player_query.is_empty() {
+        info!("Out of lives, game over");
+        next_state.set(GameState::GameOver);
+    }
+}
+
+/// Rebuilds the first level from scratch through a regular level transition
+fn restart_game(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut transition_events: EventWriter<LevelTransitionEvent>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyR) {
+        info!("Restarting from {}", START_LEVEL);
+        transition_events.write(LevelTransitionEvent {
+            map: START_LEVEL.to_string(),
+            entry: None,
+        });
+        next_state.set(GameState::Loading);
+    }
+}
+
+/// Freezes virtual time, which stops `FixedUpdate` move timing, and the physics pipeline
+fn pause_game(mut time: ResMut<Time<Virtual>>, mut rapier_config: Query<&mut RapierConfiguration>) {
+    time.pause();
+    for mut config in rapier_config.iter_mut() {
+        config.physics_pipeline_active = false;
+    }
+}
+
+fn resume_game(mut time: ResMut<Time<Virtual>>, mut rapier_config: Query<&mut RapierConfiguration>) {
+    time.unpause();
+    for mut config in rapier_config.iter_mut() {
+        config.physics_pipeline_active = true;
+    }
+}
+
+fn spawn_main_menu(mut commands: Commands) {
+    spawn_state_screen(&mut commands, "Character 2D", "Press Enter to start");
+}
+
+fn spawn_pause_screen(mut commands: Commands) {
+    spawn_state_screen(&mut commands, "Paused", "Press Escape to resume");
+}
+
+fn spawn_game_over_screen(mut commands: Commands) {
+    spawn_state_screen(&mut commands, "Game Over", "Press R to restart");
+}
+
+fn spawn_state_screen(commands: &mut Commands, title: &str, hint: &str) {
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(16.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+            StateScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(title),
+                TextFont {
+                    font_size: 64.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+            parent.spawn((
+                Text::new(hint),
+                TextFont {
+                    font_size: 24.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.8, 0.8, 0.8)),
+            ));
+        });
+}
+
+fn despawn_state_screen(mut commands: Commands, screen_query: Query<Entity, With<StateScreen>>) {
+    for screen in screen_query.iter() {
+        commands.entity(screen).despawn();
+    }
+}
@@ -16,6 +16,21 @@ pub struct GlobalEntityMap {
     pub unittype_aioptions: HashMap<UnitType, Vec<AIOption>>,
 }
 
+impl GlobalEntityMap {
+    /// Forgets a despawned unit and everything registered for its weapon
+    pub fn remove_unit(&mut self, unit: Entity) {
+        if let Some(weapon) = self.player_weapon.remove(&unit) {
+            self.weapon_player.remove(&weapon);
+            if let Some(collider) = self.weapon_collider.remove(&weapon) {
+                self.collider_weapon.remove(&collider);
+            }
+        }
+        self.player_to_collider.remove(&unit);
+        self.player_sword_trail.remove(&unit);
+        self.entity_transfrom.remove(&unit);
+    }
+}
+
 // Plugin to initialize the resource
 pub struct GlobalEntityMapPlugin;
 
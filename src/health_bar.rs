@@ -3,6 +3,8 @@ use bevy::{
 };
 // Import your HpChangeEvent from unit.rs and BerserkerHealEvent from berserker.rs
 use crate::berserker::BerserkerHealEvent;
+use crate::boss::BossFight;
+use crate::force::{Factions, Force, Relation};
 use crate::unit::HpChangeEvent;
 
 /// This example uses a shader source file from the assets subdirectory
@@ -14,7 +16,10 @@ impl Plugin for HealthBarPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(UiMaterialPlugin::<HealthBarMaterial>::default())
             .add_systems(Startup, setup)
-            .add_systems(Update, (update_health_bar, update_enemy_health_bar));
+            .add_systems(
+                Update,
+                (update_health_bar, update_enemy_health_bar, update_boss_health_bar),
+            );
     }
 }
 
@@ -24,6 +29,16 @@ pub struct HealthBar;
 #[derive(Component)]
 pub struct EnemyHealthBar;
 
+/// Wide bar shown at the bottom of the screen while a boss fight is active
+#[derive(Component)]
+pub struct BossHealthBar;
+
+#[derive(Component)]
+pub struct BossHealthBarRoot;
+
+#[derive(Component)]
+pub struct BossNameText;
+
 #[derive(Resource, Default)]
 pub struct LastHitEnemy {
     pub entity: Option<Entity>,
@@ -77,6 +92,47 @@ fn setup(mut commands: Commands, mut ui_materials: ResMut<Assets<HealthBarMateri
                 EnemyHealthBar,
             ));
         });
+
+    // Boss health bar (bottom center) - starts hidden
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                bottom: Val::Px(60.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                display: Display::None, // Start hidden
+                ..default()
+            },
+            BossHealthBarRoot,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 28.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                BossNameText,
+            ));
+
+            parent.spawn((
+                Node {
+                    width: Val::Px(900.0),
+                    height: Val::Px(30.0),
+                    border: UiRect::all(Val::Px(1.0)),
+                    ..default()
+                },
+                MaterialNode(ui_materials.add(HealthBarMaterial {
+                    fill_ratio: Vec4::new(1.0, 0.0, 0.0, 0.0),
+                    health_color: LinearRgba::from(ORANGE_RED).to_f32_array().into(), // Orange health color
+                    border_color: LinearRgba::from(GOLD).to_f32_array().into(), // Gold border
+                })),
+                BossHealthBar,
+            ));
+        });
 }
 
 #[derive(AsBindGroup, Asset, TypePath, Debug, Clone)]
@@ -202,6 +258,14 @@ fn update_health_bar(
     }
 }
 
+fn relation_color(relation: Relation) -> Vec4 {
+    match relation {
+        Relation::Hostile => LinearRgba::from(GREEN).to_f32_array().into(),
+        Relation::Neutral => LinearRgba::from(YELLOW).to_f32_array().into(),
+        Relation::Allied => LinearRgba::from(DEEP_SKY_BLUE).to_f32_array().into(),
+    }
+}
+
 // Update enemy health bar when player damages an enemy
 fn update_enemy_health_bar(
     mut materials: ResMut<Assets<HealthBarMaterial>>,
@@ -213,7 +277,20 @@ fn update_enemy_health_bar(
     >,
     player_query: Query<Entity, With<crate::Player>>,
     unit_query: Query<&crate::unit::Unit>,
+    boss_fight: Res<BossFight>,
+    force_query: Query<&Force>,
+    factions: Res<Factions>,
 ) {
+    // The boss health bar replaces the enemy health bar during a boss fight
+    if boss_fight.is_active() {
+        hp_events.clear();
+        last_hit_enemy.entity = None;
+        for (_, mut node) in enemy_health_bar_query.iter_mut() {
+            node.display = Display::None;
+        }
+        return;
+    }
+
     for event in hp_events.read() {
         debug!("Checking HpChangeEvent for enemy health bar update");
 
@@ -235,11 +312,23 @@ fn update_enemy_health_bar(
                         0.0
                     };
 
+                    // Color the bar by how the player's force relates to the damaged unit
+                    let relation = match (
+                        force_query.get(player_entity),
+                        force_query.get(event.entity),
+                    ) {
+                        (Ok(player_force), Ok(enemy_force)) => {
+                            factions.relation(player_force.force, enemy_force.force)
+                        }
+                        _ => Relation::Hostile,
+                    };
+
                     // Update the enemy health bar
                     for (material_handle, mut node) in enemy_health_bar_query.iter_mut() {
                         if let Some(material) = materials.get_mut(material_handle) {
                             debug!("Updating enemy health bar: fill_ratio = {:.2}", fill_ratio);
                             material.fill_ratio.x = fill_ratio;
+                            material.health_color = relation_color(relation);
 
                             // Show or hide the health bar based on conditions
                             if fill_ratio <= 0.0 {
@@ -275,3 +364,55 @@ fn update_enemy_health_bar(
         }
     }
 }
+
+// Show the boss health bar while a boss fight is active and keep it in sync with the boss HP
+fn update_boss_health_bar(
+    mut materials: ResMut<Assets<HealthBarMaterial>>,
+    boss_fight: Res<BossFight>,
+    boss_query: Query<(&crate::unit::Unit, &crate::boss::Boss)>,
+    mut root_query: Query<&mut Node, With<BossHealthBarRoot>>,
+    bar_query: Query<&MaterialNode<HealthBarMaterial>, With<BossHealthBar>>,
+    mut name_query: Query<&mut Text, With<BossNameText>>,
+) {
+    let boss = boss_fight
+        .boss
+        .and_then(|entity| boss_query.get(entity).ok());
+
+    for mut node in root_query.iter_mut() {
+        let display = if boss.is_some() {
+            Display::Flex
+        } else {
+            Display::None
+        };
+        if node.display != display {
+            node.display = display;
+        }
+    }
+
+    let Some((unit, boss)) = boss else {
+        return;
+    };
+
+    let label = match boss.phases.get(boss.current_phase) {
+        Some(phase) => format!("{} - {}", boss.name, phase.name),
+        None => boss.name.clone(),
+    };
+    for mut text in name_query.iter_mut() {
+        if text.0 != label {
+            text.0 = label.clone();
+        }
+    }
+
+    for material_handle in bar_query.iter() {
+        if let Some(material) = materials.get_mut(material_handle) {
+            let fill_ratio = if unit.max_hp > 0.0 {
+                (unit.hp / unit.max_hp).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            if material.fill_ratio.x != fill_ratio {
+                material.fill_ratio.x = fill_ratio;
+            }
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use bevy::prelude::*;
 
 use crate::berserker::BerserkerActiveEvent;
+use crate::companion::{CompanionCommandEvent, CompanionOrder};
 use crate::Player;
 use crate::constants::*;
 use crate::stun::Stun;
@@ -37,6 +38,7 @@ pub fn handle_input(
     mut move_events: EventWriter<MoveEvent>,
     mut action_events: EventWriter<ActionEvent>,
     mut berserker_events: EventWriter<BerserkerActiveEvent>,
+    mut companion_events: EventWriter<CompanionCommandEvent>,
     stun_query: Query<&Stun>,
 ) {
     // Check if player is stunned - if so, ignore all input
@@ -98,4 +100,22 @@ pub fn handle_input(
             entity: *player,
         });
     }
+
+    if keyboard_input.just_pressed(KeyCode::Digit1) {
+        companion_events.write(CompanionCommandEvent {
+            order: CompanionOrder::AttackTarget,
+        });
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Digit2) {
+        companion_events.write(CompanionCommandEvent {
+            order: CompanionOrder::Hold,
+        });
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Digit3) {
+        companion_events.write(CompanionCommandEvent {
+            order: CompanionOrder::Follow,
+        });
+    }
 }
@@ -0,0 +1,352 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy_ecs_tilemap::prelude::*;
+use bevy_rapier2d::prelude::RigidBodyDisabled;
+use std::collections::{HashMap, HashSet};
+use tiled::ChunkData;
+
+use crate::ai::{AI, AISuspended};
+use crate::constants::*;
+use crate::level::collision::{TileCollision, spawn_tile_colliders, tile_collision};
+use crate::level::layers::{find_layer, tile_animation};
+use crate::level::tiled::TiledMap;
+
+/// Tiles per side of a Tiled chunk
+const CHUNK_TILES: u32 = ChunkData::WIDTH;
+
+/// Root of an infinite tile layer; chunks are spawned as its children
+#[derive(Component)]
+pub struct InfiniteLayer {
+    /// Tiled layer id, the layer may be nested in a group
+    pub layer_id: u32,
+    pub map: Handle<TiledMap>,
+    /// Opacity including the parent groups'
+    pub opacity: f32,
+    pub loaded: HashMap<IVec2, Entity>,
+}
+
+impl InfiniteLayer {
+    pub fn new(layer_id: u32, map: Handle<TiledMap>, opacity: f32) -> Self {
+        Self {
+            layer_id,
+            map,
+            opacity,
+            loaded: HashMap::new(),
+        }
+    }
+}
+
+/// AI unit frozen because its chunk is far from the camera
+#[derive(Component)]
+pub struct Dormant {
+    /// Whether `AISuspended` was added for dormancy, so waking doesn't lift
+    /// a suspension someone else owns
+    suspended_ai: bool,
+}
+
+pub struct ChunkStreamingPlugin;
+
+impl Plugin for ChunkStreamingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (stream_chunks, update_dormant_units).chain());
+    }
+}
+
+/// Chunk coordinate (Tiled chunk grid, Y down) containing a world position
+pub fn world_to_chunk(position: Vec2, tile_size: Vec2) -> IVec2 {
+    let chunk_size = tile_size * CHUNK_TILES as f32;
+    // Flip Y for Tiled coordinate system
+    IVec2::new(
+        (position.x / chunk_size.x).floor() as i32,
+        (-position.y / chunk_size.y).floor() as i32,
+    )
+}
+
+fn in_radius(chunk: IVec2, center: IVec2, radius: i32) -> bool {
+    (chunk.x - center.x).abs() <= radius && (chunk.y - center.y).abs() <= radius
+}
+
+fn stream_chunks(
+    mut commands: Commands,
+    mut layer_query: Query<(Entity, &mut InfiniteLayer)>,
+    camera_query: Query<&Transform, With<Camera2d>>,
+    maps: Res<Assets<TiledMap>>,
+) {
+    let Ok(camera_transform) = camera_query.single() else {
+        return;
+    };
+    let camera_position = camera_transform.translation.xy();
+
+    for (layer_entity, mut infinite_layer) in layer_query.iter_mut() {
+        let Some(tiled_map) = maps.get(&infinite_layer.map) else {
+            continue;
+        };
+        let tile_size = Vec2::new(
+            tiled_map.map.tile_width as f32,
+            tiled_map.map.tile_height as f32,
+        );
+        let center = world_to_chunk(camera_position, tile_size);
+
+        // Unload with a wider radius than we load to avoid thrashing on chunk borders
+        let far_chunks: Vec<IVec2> = infinite_layer
+            .loaded
+            .keys()
+            .filter(|chunk| !in_radius(**chunk, center, CHUNK_UNLOAD_RADIUS))
+            .copied()
+            .collect();
+        for chunk in far_chunks {
+            if let Some(chunk_entity) = infinite_layer.loaded.remove(&chunk) {
+                debug!("Unloading chunk {:?}", chunk);
+                commands.entity(chunk_entity).despawn();
+            }
+        }
+
+        let Some(tiled::TileLayer::Infinite(layer_data)) =
+            find_layer(&tiled_map.map, infinite_layer.layer_id).and_then(|layer| layer.as_tile_layer())
+        else {
+            continue;
+        };
+
+        for y in -CHUNK_LOAD_RADIUS..=CHUNK_LOAD_RADIUS {
+            for x in -CHUNK_LOAD_RADIUS..=CHUNK_LOAD_RADIUS {
+                let chunk = center + IVec2::new(x, y);
+                if infinite_layer.loaded.contains_key(&chunk) {
+                    continue;
+                }
+                let Some(chunk_data) = layer_data.get_chunk(chunk.x, chunk.y) else {
+                    continue;
+                };
+                let chunk_entity = spawn_chunk(
+                    &mut commands,
+                    tiled_map,
+                    &chunk_data,
+                    chunk,
+                    tile_size,
+                    infinite_layer.opacity,
+                );
+                commands.entity(layer_entity).add_child(chunk_entity);
+                infinite_layer.loaded.insert(chunk, chunk_entity);
+            }
+        }
+    }
+}
+
+/// Spawns one tilemap per tileset used in the chunk, plus its colliders
+fn spawn_chunk(
+    commands: &mut Commands,
+    tiled_map: &TiledMap,
+    chunk_data: &tiled::Chunk,
+    chunk: IVec2,
+    tile_size: Vec2,
+    opacity: f32,
+) -> Entity {
+    debug!("Loading chunk {:?}", chunk);
+    let map_size = TilemapSize {
+        x: CHUNK_TILES,
+        y: CHUNK_TILES,
+    };
+    let grid_size = TilemapGridSize {
+        x: tile_size.x,
+        y: tile_size.y,
+    };
+    // Bottom-left corner of the chunk; Tiled rows go down
+    let origin = Vec2::new(
+        chunk.x as f32 * CHUNK_TILES as f32 * tile_size.x,
+        -((chunk.y + 1) as f32 * CHUNK_TILES as f32 * tile_size.y),
+    );
+    let chunk_entity = commands
+        .spawn((
+            Transform::from_translation(origin.extend(0.0)),
+            Visibility::default(),
+            Name::new(format!("Chunk {} {}", chunk.x, chunk.y)),
+        ))
+        .id();
+
+    let mut tileset_tiles: HashMap<usize, Vec<_>> = HashMap::new();
+    let mut solid_tiles = vec![false; (CHUNK_TILES * CHUNK_TILES) as usize];
+    let mut custom_shapes = Vec::new();
+    for x in 0..CHUNK_TILES {
+        for y in 0..CHUNK_TILES {
+            let mapped_y = (CHUNK_TILES - 1 - y) as i32;
+            let (Some(layer_tile), Some(layer_tile_data)) = (
+                chunk_data.get_tile(x as i32, mapped_y),
+                chunk_data.get_tile_data(x as i32, mapped_y),
+            ) else {
+                continue;
+            };
+
+            let tileset = layer_tile.get_tileset();
+            let tile = layer_tile.get_tile();
+            match tile.as_ref().and_then(|tile| {
+                tile_collision(
+                    tile,
+                    layer_tile.id(),
+                    tileset.tile_width as f32,
+                    tileset.tile_height as f32,
+                )
+            }) {
+                Some(TileCollision::Full) => solid_tiles[(y * CHUNK_TILES + x) as usize] = true,
+                Some(TileCollision::Shapes(shapes)) => {
+                    let tile_origin = Vec2::new(x as f32, y as f32) * tile_size;
+                    custom_shapes.extend(
+                        shapes
+                            .into_iter()
+                            .map(|(position, collider)| (tile_origin + position, collider)),
+                    );
+                }
+                None => {}
+            }
+
+            tileset_tiles
+                .entry(layer_tile.tileset_index())
+                .or_default()
+                .push((
+                    TilePos { x, y },
+                    layer_tile.id(),
+                    TileFlip {
+                        x: layer_tile_data.flip_h,
+                        y: layer_tile_data.flip_v,
+                        d: layer_tile_data.flip_d,
+                    },
+                    tile.as_ref().and_then(tile_animation),
+                ));
+        }
+    }
+
+    for (tileset_index, tiles) in tileset_tiles {
+        let tileset = &tiled_map.map.tilesets()[tileset_index];
+        let tileset_z = tileset_index as f32 * TILESET_Z_STEP;
+        let Some(texture) = tiled_map.tilemap_textures.get(&tileset_index) else {
+            // Image collection: every tile has its own image, draw them as sprites
+            for (tile_pos, tile_id, flip, _) in tiles {
+                let Some(image) = tiled_map.tile_images.get(&(tileset_index, tile_id)) else {
+                    warn!("No image for tile {} in tileset '{}'", tile_id, tileset.name);
+                    continue;
+                };
+                let sprite = commands
+                    .spawn((
+                        Sprite {
+                            image: image.clone(),
+                            color: Color::WHITE.with_alpha(opacity),
+                            flip_x: flip.x,
+                            flip_y: flip.y,
+                            anchor: Anchor::BottomLeft,
+                            ..Default::default()
+                        },
+                        Transform::from_xyz(
+                            tile_pos.x as f32 * tile_size.x,
+                            tile_pos.y as f32 * tile_size.y,
+                            tileset_z,
+                        ),
+                    ))
+                    .id();
+                commands.entity(chunk_entity).add_child(sprite);
+            }
+            continue;
+        };
+        let tilemap_entity = commands.spawn_empty().id();
+        let mut tile_storage = TileStorage::empty(map_size);
+
+        for (tile_pos, tile_id, flip, animation) in tiles {
+            let tile_entity = commands
+                .spawn(TileBundle {
+                    position: tile_pos,
+                    tilemap_id: TilemapId(tilemap_entity),
+                    texture_index: TileTextureIndex(tile_id),
+                    flip,
+                    color: TileColor(Color::WHITE.with_alpha(opacity)),
+                    ..Default::default()
+                })
+                .id();
+            if let Some(animation) = animation {
+                commands.entity(tile_entity).insert(animation);
+            }
+            tile_storage.set(&tile_pos, tile_entity);
+            // Parent tiles so unloading the chunk takes them along
+            commands.entity(tilemap_entity).add_child(tile_entity);
+        }
+
+        commands.entity(tilemap_entity).insert(TilemapBundle {
+            grid_size,
+            size: map_size,
+            storage: tile_storage,
+            texture: texture.clone(),
+            tile_size: TilemapTileSize {
+                x: tileset.tile_width as f32,
+                y: tileset.tile_height as f32,
+            },
+            spacing: TilemapSpacing {
+                x: tileset.spacing as f32,
+                y: tileset.spacing as f32,
+            },
+            anchor: TilemapAnchor::BottomLeft,
+            map_type: TilemapType::Square,
+            transform: Transform::from_xyz(0.0, 0.0, tileset_z),
+            ..Default::default()
+        });
+        commands.entity(chunk_entity).add_child(tilemap_entity);
+    }
+
+    spawn_tile_colliders(
+        commands,
+        chunk_entity,
+        &solid_tiles,
+        custom_shapes,
+        UVec2::splat(CHUNK_TILES),
+        tile_size,
+        Vec2::ZERO,
+    );
+
+    chunk_entity
+}
+
+/// Freezes AI units outside the streamed area and wakes them when it comes back
+fn update_dormant_units(
+    mut commands: Commands,
+    layer_query: Query<&InfiniteLayer>,
+    unit_query: Query<(Entity, &Transform, Option<&Dormant>, Has<AISuspended>), With<AI>>,
+    camera_query: Query<&Transform, With<Camera2d>>,
+    maps: Res<Assets<TiledMap>>,
+) {
+    // Only maps with infinite layers stream, finite maps keep everything awake
+    let Some(infinite_layer) = layer_query.iter().next() else {
+        return;
+    };
+    let (Ok(camera_transform), Some(tiled_map)) =
+        (camera_query.single(), maps.get(&infinite_layer.map))
+    else {
+        return;
+    };
+    let tile_size = Vec2::new(
+        tiled_map.map.tile_width as f32,
+        tiled_map.map.tile_height as f32,
+    );
+    let center = world_to_chunk(camera_transform.translation.xy(), tile_size);
+    let active: HashSet<IVec2> = infinite_layer.loaded.keys().copied().collect();
+
+    for (entity, transform, dormant, ai_suspended) in unit_query.iter() {
+        let chunk = world_to_chunk(transform.translation.xy(), tile_size);
+        let awake = active.contains(&chunk) || in_radius(chunk, center, CHUNK_LOAD_RADIUS);
+
+        match (awake, dormant) {
+            (false, None) => {
+                debug!("Unit {:?} in unloaded chunk {:?}, suspending", entity, chunk);
+                commands.entity(entity).insert((
+                    Dormant {
+                        suspended_ai: !ai_suspended,
+                    },
+                    AISuspended,
+                    RigidBodyDisabled,
+                ));
+            }
+            (true, Some(dormant)) => {
+                debug!("Unit {:?} back in loaded chunk {:?}, waking", entity, chunk);
+                commands.entity(entity).remove::<(Dormant, RigidBodyDisabled)>();
+                if dormant.suspended_ai {
+                    commands.entity(entity).remove::<AISuspended>();
+                }
+            }
+            _ => {}
+        }
+    }
+}
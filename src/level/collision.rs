@@ -0,0 +1,226 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use tiled::{ObjectShape, PropertyValue};
+
+/// Collision generated for a single tile
+pub enum TileCollision {
+    /// Covers the whole tile and can be merged with its neighbours
+    Full,
+    /// Custom shapes in tile-local coordinates (origin at the tile's bottom-left, Y up)
+    Shapes(Vec<(Vec2, Collider)>),
+}
+
+/// Marker for the fixed body holding a tile layer's colliders
+#[derive(Component)]
+pub struct TileColliders;
+
+/// Reads the tile's `solid` property or the collision objectgroup from the tileset.
+///
+/// `tile_id` is the tile's id in its tileset, only used for logging.
+pub fn tile_collision(
+    tile: &tiled::Tile,
+    tile_id: u32,
+    tile_width: f32,
+    tile_height: f32,
+) -> Option<TileCollision> {
+    if let Some(PropertyValue::BoolValue(true)) = tile.properties.get("solid") {
+        return Some(TileCollision::Full);
+    }
+
+    let collision = tile.collision.as_ref()?;
+    let objects = collision.object_data();
+    if objects.is_empty() {
+        return None;
+    }
+
+    let mut shapes = Vec::new();
+    for object in objects {
+        if let ObjectShape::Rect { width, height } = object.shape {
+            let covers_tile = objects.len() == 1
+                && object.x <= 0.0
+                && object.y <= 0.0
+                && object.x + width >= tile_width
+                && object.y + height >= tile_height;
+            if covers_tile {
+                return Some(TileCollision::Full);
+            }
+        }
+
+        match object_shape_collider(&object.shape) {
+            // Tiled object coordinates are relative to the tile's top-left corner with Y down
+            Some((offset, collider)) => {
+                let origin = Vec2::new(object.x, tile_height - object.y);
+                shapes.push((origin + offset, collider));
+            }
+            None => debug!("Unsupported collision shape on tile {}", tile_id),
+        }
+    }
+
+    if shapes.is_empty() {
+        None
+    } else {
+        Some(TileCollision::Shapes(shapes))
+    }
+}
+
+/// Builds a collider for a Tiled object shape.
+///
+/// Returns the collider's offset from the object origin (Y up) alongside it. Ellipses
+/// are approximated with a ball or capsule.
+pub fn object_shape_collider(shape: &ObjectShape) -> Option<(Vec2, Collider)> {
+    match shape {
+        ObjectShape::Rect { width, height } => Some((
+            Vec2::new(width / 2.0, -height / 2.0),
+            Collider::cuboid(width / 2.0, height / 2.0),
+        )),
+        ObjectShape::Ellipse { width, height } => {
+            let collider = if (width - height).abs() < f32::EPSILON {
+                Collider::ball(width / 2.0)
+            } else if width > height {
+                Collider::capsule_x((width - height) / 2.0, height / 2.0)
+            } else {
+                Collider::capsule_y((height - width) / 2.0, width / 2.0)
+            };
+            Some((Vec2::new(width / 2.0, -height / 2.0), collider))
+        }
+        ObjectShape::Polygon { points } => {
+            let vertices: Vec<Vec2> = points.iter().map(|(x, y)| Vec2::new(*x, -*y)).collect();
+            let collider = Collider::convex_hull(&vertices);
+            if collider.is_none() {
+                warn!("Could not build a convex collider from {} points", points.len());
+            }
+            collider.map(|collider| (Vec2::ZERO, collider))
+        }
+        ObjectShape::Polyline { points } => {
+            let vertices: Vec<Vec2> = points.iter().map(|(x, y)| Vec2::new(*x, -*y)).collect();
+            Some((Vec2::ZERO, Collider::polyline(vertices, None)))
+        }
+        _ => None,
+    }
+}
+
+/// Greedily merges solid cells into as few rectangles as possible.
+///
+/// `solid` is row-major with `width * height` cells. Returned rects are in cell units,
+/// `min` inclusive and `max` exclusive.
+pub fn merge_solid_tiles(solid: &[bool], width: u32, height: u32) -> Vec<URect> {
+    let index = |x: u32, y: u32| (y * width + x) as usize;
+    let mut used = vec![false; solid.len()];
+    let mut rects = Vec::new();
+
+    for y in 0..height {
+        for x in 0..width {
+            if !solid[index(x, y)] || used[index(x, y)] {
+                continue;
+            }
+
+            // Grow right as far as possible
+            let mut max_x = x + 1;
+            while max_x < width && solid[index(max_x, y)] && !used[index(max_x, y)] {
+                max_x += 1;
+            }
+
+            // Then grow up while the whole row span stays solid
+            let mut max_y = y + 1;
+            'grow: while max_y < height {
+                for cx in x..max_x {
+                    if !solid[index(cx, max_y)] || used[index(cx, max_y)] {
+                        break 'grow;
+                    }
+                }
+                max_y += 1;
+            }
+
+            for cy in y..max_y {
+                for cx in x..max_x {
+                    used[index(cx, cy)] = true;
+                }
+            }
+            rects.push(URect::new(x, y, max_x, max_y));
+        }
+    }
+
+    rects
+}
+
+/// Spawns a fixed body under `layer_entity` holding the merged solid rectangles and the
+/// custom per-tile shapes, laid out from `origin` in the layer's local coordinates.
+pub fn spawn_tile_colliders(
+    commands: &mut Commands,
+    layer_entity: Entity,
+    solid: &[bool],
+    custom_shapes: Vec<(Vec2, Collider)>,
+    map_size: UVec2,
+    grid_size: Vec2,
+    origin: Vec2,
+) -> usize {
+    let rects = merge_solid_tiles(solid, map_size.x, map_size.y);
+    if rects.is_empty() && custom_shapes.is_empty() {
+        return 0;
+    }
+
+    let collider_count = rects.len() + custom_shapes.len();
+    let body = commands
+        .spawn((
+            TileColliders,
+            RigidBody::Fixed,
+            Transform::from_translation(origin.extend(0.0)),
+            Visibility::default(),
+            Name::new("TileColliders"),
+        ))
+        .with_children(|parent| {
+            for rect in rects {
+                let size = rect.size().as_vec2() * grid_size;
+                let min = rect.min.as_vec2() * grid_size;
+                let center = min + size / 2.0;
+                parent.spawn((
+                    Collider::cuboid(size.x / 2.0, size.y / 2.0),
+                    Transform::from_translation(center.extend(0.0)),
+                ));
+            }
+            for (position, collider) in custom_shapes {
+                parent.spawn((collider, Transform::from_translation(position.extend(0.0))));
+            }
+        })
+        .id();
+    commands.entity(layer_entity).add_child(body);
+
+    collider_count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_solid_row_and_block() {
+        // 4x3 grid:
+        // y=2  . . . .
+        // y=1  # # . .
+        // y=0  # # # #
+        let solid = [
+            true, true, true, true, //
+            true, true, false, false, //
+            false, false, false, false,
+        ];
+        let rects = merge_solid_tiles(&solid, 4, 3);
+        assert_eq!(rects, vec![URect::new(0, 0, 4, 1), URect::new(0, 1, 2, 2)]);
+    }
+
+    #[test]
+    fn test_merge_covers_every_solid_cell_once() {
+        let solid = [
+            true, false, true, //
+            true, true, true, //
+            false, true, false,
+        ];
+        let rects = merge_solid_tiles(&solid, 3, 3);
+        let covered: u32 = rects.iter().map(|r| r.width() * r.height()).sum();
+        assert_eq!(covered, solid.iter().filter(|s| **s).count() as u32);
+    }
+
+    #[test]
+    fn test_merge_empty_grid() {
+        assert!(merge_solid_tiles(&[false; 6], 3, 2).is_empty());
+    }
+}
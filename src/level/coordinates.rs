@@ -0,0 +1,277 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::{HexCoordSystem, IsoCoordSystem, TilePos, TilemapSize, TilemapType};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MapOrientation {
+    #[default]
+    Orthogonal,
+    Isometric,
+    Staggered,
+    Hexagonal,
+}
+
+/// Layout of a Tiled map, for converting Tiled coordinates into world coordinates.
+///
+/// Tiled measures right and down from the map's top-left corner while the world is Y up,
+/// so maps extend into negative Y from their origin. Staggered and hexagonal maps must
+/// stagger along the Y axis, and hexagons are expected to be regular with a side length of
+/// half the tile height.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MapGeometry {
+    pub orientation: MapOrientation,
+    /// Size in tiles
+    pub width: u32,
+    pub height: u32,
+    pub tile_size: Vec2,
+    /// Whether odd or even rows of staggered and hexagonal maps are shifted right
+    pub stagger_index: tiled::StaggerIndex,
+}
+
+impl MapGeometry {
+    pub fn from_map(map: &tiled::Map) -> Self {
+        let orientation = match map.orientation {
+            tiled::Orientation::Orthogonal => MapOrientation::Orthogonal,
+            tiled::Orientation::Isometric => MapOrientation::Isometric,
+            tiled::Orientation::Staggered => MapOrientation::Staggered,
+            tiled::Orientation::Hexagonal => MapOrientation::Hexagonal,
+        };
+        let staggered = matches!(
+            orientation,
+            MapOrientation::Staggered | MapOrientation::Hexagonal
+        );
+        if staggered && map.stagger_axis == tiled::StaggerAxis::X {
+            warn!(
+                "Map staggers along the X axis, which is not supported; laying it out with \
+                 staggered rows instead"
+            );
+        }
+        Self {
+            orientation,
+            width: map.width,
+            height: map.height,
+            tile_size: Vec2::new(map.tile_width as f32, map.tile_height as f32),
+            stagger_index: map.stagger_index,
+        }
+    }
+
+    /// Converts an object position (or polygon point) from Tiled to world coordinates
+    pub fn object_to_world(&self, position: Vec2) -> Vec2 {
+        let screen = match self.orientation {
+            MapOrientation::Isometric => {
+                // Isometric objects live in tile space, scaled by the tile height on both axes
+                let tile = position / self.tile_size.y;
+                Vec2::new(
+                    (tile.x - tile.y) * self.tile_size.x / 2.0 + self.isometric_origin_x(),
+                    (tile.x + tile.y) * self.tile_size.y / 2.0,
+                )
+            }
+            _ => position,
+        };
+        Vec2::new(screen.x, -screen.y)
+    }
+
+    /// World position of the centre of the tile at Tiled tile coordinates `(x, y)`
+    pub fn tile_center(&self, x: i32, y: i32) -> Vec2 {
+        let (tile_x, tile_y) = (x as f32, y as f32);
+        let Vec2 { x: width, y: height } = self.tile_size;
+        let screen = match self.orientation {
+            MapOrientation::Orthogonal => Vec2::new((tile_x + 0.5) * width, (tile_y + 0.5) * height),
+            MapOrientation::Isometric => Vec2::new(
+                (tile_x - tile_y) * width / 2.0 + self.isometric_origin_x(),
+                (tile_x + tile_y + 1.0) * height / 2.0,
+            ),
+            MapOrientation::Staggered | MapOrientation::Hexagonal => {
+                let shift = if self.is_shifted_row(y) { width / 2.0 } else { 0.0 };
+                Vec2::new(
+                    (tile_x + 0.5) * width + shift,
+                    tile_y * self.row_height() + height / 2.0,
+                )
+            }
+        };
+        Vec2::new(screen.x, -screen.y)
+    }
+
+    /// Tilemap type that lays tiles out like Tiled does, given `tile_pos`
+    pub fn tilemap_type(&self) -> TilemapType {
+        match self.orientation {
+            MapOrientation::Orthogonal => TilemapType::Square,
+            MapOrientation::Isometric => TilemapType::Isometric(IsoCoordSystem::Diamond),
+            MapOrientation::Staggered => TilemapType::Isometric(IsoCoordSystem::Staggered),
+            MapOrientation::Hexagonal => TilemapType::Hexagon(HexCoordSystem::RowOdd),
+        }
+    }
+
+    pub fn tilemap_size(&self) -> TilemapSize {
+        match self.orientation {
+            MapOrientation::Isometric => TilemapSize {
+                x: self.height,
+                y: self.width,
+            },
+            _ => TilemapSize {
+                x: self.width,
+                y: self.tilemap_rows(),
+            },
+        }
+    }
+
+    /// Tilemap position of the Tiled tile `(x, y)`.
+    ///
+    /// Rows are flipped since the tilemap grows up and Tiled grows down; diamond
+    /// isometric maps also swap axes so the diamond keeps its orientation.
+    pub fn tile_pos(&self, x: u32, y: u32) -> TilePos {
+        match self.orientation {
+            MapOrientation::Orthogonal => TilePos {
+                x,
+                y: self.height - 1 - y,
+            },
+            MapOrientation::Isometric => TilePos {
+                x: self.height - 1 - y,
+                y: self.width - 1 - x,
+            },
+            MapOrientation::Staggered | MapOrientation::Hexagonal => TilePos {
+                x,
+                y: self.tilemap_rows() - 1 - y,
+            },
+        }
+    }
+
+    /// Translation of a tile layer's tilemap, relative to the layer, that lines its tiles up
+    /// with `tile_center`. Assumes `TilemapAnchor::None`, which centres tile (0, 0) on the origin.
+    pub fn tilemap_origin(&self) -> Vec2 {
+        let (width, height) = (self.width as i32, self.height as i32);
+        match self.orientation {
+            MapOrientation::Orthogonal => self.tile_center(0, height - 1),
+            MapOrientation::Isometric => self.tile_center(width - 1, height - 1),
+            MapOrientation::Staggered | MapOrientation::Hexagonal => {
+                self.tile_center(0, self.tilemap_rows() as i32 - 1)
+            }
+        }
+    }
+
+    /// Bottom-left corner of an orthogonal map, where tile colliders are laid out from
+    pub fn bottom_left(&self) -> Vec2 {
+        Vec2::new(0.0, -(self.height as f32) * self.tile_size.y)
+    }
+
+    /// Distance between row centres in Tiled pixels
+    fn row_height(&self) -> f32 {
+        match self.orientation {
+            MapOrientation::Staggered => self.tile_size.y / 2.0,
+            // Rows overlap by the hexagon's side length
+            MapOrientation::Hexagonal => (self.tile_size.y + self.tile_size.y / 2.0) / 2.0,
+            _ => self.tile_size.y,
+        }
+    }
+
+    /// Whether Tiled shifts row `y` of a staggered or hexagonal map right
+    fn is_shifted_row(&self, y: i32) -> bool {
+        (y.rem_euclid(2) == 1) == (self.stagger_index == tiled::StaggerIndex::Odd)
+    }
+
+    /// Rows in the tilemap. The tilemap shifts its odd rows, so staggered maps get a spare
+    /// row when needed for flipping rows to turn the shifted Tiled rows into odd ones.
+    fn tilemap_rows(&self) -> u32 {
+        let shifted_rows_odd = self.stagger_index == tiled::StaggerIndex::Odd;
+        match self.orientation {
+            MapOrientation::Staggered | MapOrientation::Hexagonal
+                if (self.height % 2 == 0) == shifted_rows_odd =>
+            {
+                self.height + 1
+            }
+            _ => self.height,
+        }
+    }
+
+    /// X of the top corner of a diamond isometric map
+    fn isometric_origin_x(&self) -> f32 {
+        self.height as f32 * self.tile_size.x / 2.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn geometry(orientation: MapOrientation, tile_size: Vec2) -> MapGeometry {
+        MapGeometry {
+            orientation,
+            width: 10,
+            height: 10,
+            tile_size,
+            stagger_index: tiled::StaggerIndex::Odd,
+        }
+    }
+
+    #[test]
+    fn orthogonal_flips_y() {
+        let map = geometry(MapOrientation::Orthogonal, Vec2::new(32.0, 32.0));
+        assert_eq!(map.object_to_world(Vec2::new(64.0, 96.0)), Vec2::new(64.0, -96.0));
+        assert_eq!(map.tile_center(0, 0), Vec2::new(16.0, -16.0));
+        assert_eq!(map.tile_center(9, 9), Vec2::new(304.0, -304.0));
+    }
+
+    #[test]
+    fn isometric_objects_use_tile_space() {
+        let map = geometry(MapOrientation::Isometric, Vec2::new(64.0, 32.0));
+        // Object origin (0, 0) is the top corner of the map
+        assert_eq!(map.object_to_world(Vec2::ZERO), Vec2::new(320.0, 0.0));
+        // One tile along both axes is the bottom corner of tile (0, 0)
+        assert_eq!(map.object_to_world(Vec2::new(32.0, 32.0)), Vec2::new(320.0, -32.0));
+        // One tile along x is the right corner of tile (0, 0)
+        assert_eq!(map.object_to_world(Vec2::new(32.0, 0.0)), Vec2::new(352.0, -16.0));
+        assert_eq!(map.tile_center(0, 0), Vec2::new(320.0, -16.0));
+        assert_eq!(map.tile_center(1, 0), Vec2::new(352.0, -32.0));
+        assert_eq!(map.tile_center(0, 1), Vec2::new(288.0, -32.0));
+    }
+
+    #[test]
+    fn staggered_and_hexagonal_shift_odd_rows() {
+        let staggered = geometry(MapOrientation::Staggered, Vec2::new(64.0, 32.0));
+        assert_eq!(staggered.tile_center(0, 0), Vec2::new(32.0, -16.0));
+        assert_eq!(staggered.tile_center(0, 1), Vec2::new(64.0, -32.0));
+        assert_eq!(staggered.object_to_world(Vec2::new(10.0, 20.0)), Vec2::new(10.0, -20.0));
+
+        let hexagonal = geometry(MapOrientation::Hexagonal, Vec2::new(32.0, 32.0));
+        assert_eq!(hexagonal.tile_center(0, 1), Vec2::new(32.0, -40.0));
+        assert_eq!(hexagonal.tile_center(1, 2), Vec2::new(48.0, -64.0));
+    }
+
+    #[test]
+    fn tilemap_lines_up_with_tile_centers() {
+        // Square and diamond layouts of bevy_ecs_tilemap with tile (0, 0) at the origin
+        let square = geometry(MapOrientation::Orthogonal, Vec2::new(32.0, 32.0));
+        let diamond = geometry(MapOrientation::Isometric, Vec2::new(64.0, 32.0));
+        for (x, y) in [(0, 0), (3, 7), (9, 9)] {
+            let pos = square.tile_pos(x, y);
+            let local = Vec2::new(pos.x as f32, pos.y as f32) * square.tile_size;
+            assert_eq!(square.tilemap_origin() + local, square.tile_center(x as i32, y as i32));
+
+            let pos = diamond.tile_pos(x, y);
+            let (a, b) = (pos.x as f32, pos.y as f32);
+            let local = Vec2::new((a - b) * 32.0, (a + b) * 16.0);
+            assert_eq!(diamond.tilemap_origin() + local, diamond.tile_center(x as i32, y as i32));
+        }
+    }
+
+    #[test]
+    fn staggered_rows_keep_parity() {
+        let map = geometry(MapOrientation::Hexagonal, Vec2::new(32.0, 32.0));
+        assert_eq!(map.tilemap_size(), TilemapSize { x: 10, y: 11 });
+        assert_eq!(map.tile_pos(0, 1).y % 2, 1);
+        assert_eq!(map.tile_pos(0, 0).y % 2, 0);
+    }
+
+    #[test]
+    fn even_stagger_index_shifts_even_rows() {
+        let map = MapGeometry {
+            stagger_index: tiled::StaggerIndex::Even,
+            ..geometry(MapOrientation::Staggered, Vec2::new(64.0, 32.0))
+        };
+        assert_eq!(map.tile_center(0, 0), Vec2::new(64.0, -16.0));
+        assert_eq!(map.tile_center(0, 1), Vec2::new(32.0, -32.0));
+        // Shifted Tiled rows still land on the tilemap's odd rows
+        assert_eq!(map.tilemap_size(), TilemapSize { x: 10, y: 10 });
+        assert_eq!(map.tile_pos(0, 0).y % 2, 1);
+        assert_eq!(map.tile_pos(0, 1).y % 2, 0);
+    }
+}
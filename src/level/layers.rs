@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::AnimatedTile;
+
+/// A Tiled layer with the offsets, opacity, visibility and parallax of its parent groups applied
+pub struct FlatLayer<'map> {
+    pub layer: tiled::Layer<'map>,
+    /// Accumulated offset in Tiled coordinates (Y down)
+    pub offset: Vec2,
+    pub opacity: f32,
+    pub visible: bool,
+    pub parallax: Vec2,
+}
+
+/// Flattens nested group layers into draw order, dropping the groups themselves
+pub fn flatten_layers<'map>(layers: impl Iterator<Item = tiled::Layer<'map>>) -> Vec<FlatLayer<'map>> {
+    let mut flat = Vec::new();
+    flatten_into(layers, Vec2::ZERO, 1.0, true, Vec2::ONE, &mut flat);
+    flat
+}
+
+fn flatten_into<'map>(
+    layers: impl Iterator<Item = tiled::Layer<'map>>,
+    offset: Vec2,
+    opacity: f32,
+    visible: bool,
+    parallax: Vec2,
+    flat: &mut Vec<FlatLayer<'map>>,
+) {
+    for layer in layers {
+        let offset = offset + Vec2::new(layer.offset_x, layer.offset_y);
+        let opacity = opacity * layer.opacity;
+        let visible = visible && layer.visible;
+        let parallax = parallax * Vec2::new(layer.parallax_x, layer.parallax_y);
+
+        if let tiled::LayerType::Group(group) = layer.layer_type() {
+            flatten_into(group.layers(), offset, opacity, visible, parallax, flat);
+        } else {
+            flat.push(FlatLayer {
+                layer,
+                offset,
+                opacity,
+                visible,
+                parallax,
+            });
+        }
+    }
+}
+
+/// Finds a layer by id, looking inside group layers
+pub fn find_layer(map: &tiled::Map, id: u32) -> Option<tiled::Layer<'_>> {
+    flatten_layers(map.layers())
+        .into_iter()
+        .map(|flat| flat.layer)
+        .find(|layer| layer.id() == id)
+}
+
+/// Layer that scrolls at a different speed than the map, relative to the camera.
+///
+/// A factor of 1 moves with the map, 0 stays fixed on screen.
+#[derive(Component)]
+pub struct Parallax {
+    pub factor: Vec2,
+    /// Layer position when the camera is at the origin
+    pub origin: Vec2,
+}
+
+impl Parallax {
+    pub fn new(factor: Vec2, origin: Vec2) -> Option<Self> {
+        (factor != Vec2::ONE).then_some(Self { factor, origin })
+    }
+
+    pub fn position(&self, camera: Vec2) -> Vec2 {
+        self.origin + camera * (Vec2::ONE - self.factor)
+    }
+}
+
+/// Converts a Tiled tile animation into `bevy_ecs_tilemap`'s shader animation.
+///
+/// The tilemap animates over a contiguous range of atlas indices at a fixed rate, so
+/// only animations whose frames follow each other with equal durations are supported;
+/// anything else shows its first frame.
+pub fn tile_animation(tile: &tiled::Tile) -> Option<AnimatedTile> {
+    let frames = tile.animation.as_ref()?;
+    let first = frames.first()?;
+
+    let contiguous = frames
+        .iter()
+        .enumerate()
+        .all(|(index, frame)| frame.tile_id == first.tile_id + index as u32);
+    let uniform = frames.iter().all(|frame| frame.duration == first.duration);
+    if !contiguous || !uniform || first.duration == 0 {
+        warn!(
+            "Tile animation starting at {} needs contiguous frames with equal durations, showing the first frame",
+            first.tile_id
+        );
+        return None;
+    }
+
+    Some(AnimatedTile {
+        start: first.tile_id,
+        end: first.tile_id + frames.len() as u32,
+        // Frames per second
+        speed: 1000.0 / first.duration as f32,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parallax_follows_camera_by_factor() {
+        let parallax = Parallax::new(Vec2::new(0.5, 0.0), Vec2::new(10.0, 20.0)).unwrap();
+        assert_eq!(parallax.position(Vec2::ZERO), Vec2::new(10.0, 20.0));
+        assert_eq!(parallax.position(Vec2::new(100.0, 100.0)), Vec2::new(60.0, 120.0));
+    }
+
+    #[test]
+    fn no_parallax_for_map_speed_layers() {
+        assert!(Parallax::new(Vec2::ONE, Vec2::ZERO).is_none());
+    }
+}
@@ -1,21 +1,193 @@
 use bevy::prelude::*;
+use bevy_rapier2d::prelude::Velocity;
+use std::collections::HashMap;
+use tiled::PropertyValue;
 
-use crate::level::tiled::{TiledMapBundle, TiledMapHandle};
+use crate::Player;
+use crate::constants::*;
+use crate::game_state::{GameState, GameplaySet};
+use crate::level::tiled::{LevelData, ObjectLayers, TiledMapBundle, TiledMapHandle, TiledMapLoadState};
+use crate::level::zone::EnterZoneEvent;
 
 
 pub struct LevelPlugin;
 impl Plugin for LevelPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup);
+        app.insert_resource(CurrentLevel::new(START_LEVEL))
+            .init_resource::<EntryPoints>()
+            .add_event::<LevelTransitionEvent>()
+            .add_systems(Startup, register_entry_point_layer_system)
+            .add_systems(OnEnter(GameState::Loading), setup)
+            .add_systems(
+                Update,
+                (door_system.in_set(GameplaySet), level_transition_system).chain(),
+            );
     }
 }
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    info!("Loading tiled map from: map/level.tmx");
-    let map_handle = TiledMapHandle(asset_server.load("map/level.tmx"));
+/// Units and other entities that belong to the loaded map and go away with it
+#[derive(Component)]
+pub struct LevelEntity;
+
+/// The map that is loaded or loading, and where the player should arrive in it
+#[derive(Resource)]
+pub struct CurrentLevel {
+    pub map: String,
+    pub entry: Option<String>,
+    /// Doors are ignored until this runs out, so arriving next to one doesn't bounce back
+    pub arrival_timer: Timer,
+}
+
+impl CurrentLevel {
+    pub fn new(map: impl Into<String>) -> Self {
+        Self {
+            map: map.into(),
+            entry: None,
+            arrival_timer: Timer::from_seconds(DOOR_ARRIVAL_COOLDOWN, TimerMode::Once),
+        }
+    }
+}
+
+/// Named arrival points from the EntryPoint layer of the current map
+#[derive(Resource, Default)]
+pub struct EntryPoints {
+    pub points: HashMap<String, Vec2>,
+}
+
+/// Request to leave the current map for `map`, arriving at the entry point `entry`
+#[derive(Event, Clone)]
+pub struct LevelTransitionEvent {
+    pub map: String,
+    /// `None` keeps the player where the map's spawn points put it
+    pub entry: Option<String>,
+}
+
+/// Spawns the map entity the first time the game loads; later loads go through transitions
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    current_level: Res<CurrentLevel>,
+    map_query: Query<(), With<TiledMapHandle>>,
+) {
+    if !map_query.is_empty() {
+        return;
+    }
+    info!("Loading tiled map from: {}", current_level.map);
+    let map_handle = TiledMapHandle(asset_server.load(current_level.map.clone()));
 
     commands.spawn(TiledMapBundle {
         tiled_map: map_handle,
         ..Default::default()
     });
-}
\ No newline at end of file
+}
+
+fn register_entry_point_layer_system(
+    mut commands: Commands,
+    mut object_layers: ResMut<ObjectLayers>,
+) {
+    let load_entry_points_system = commands.register_system(load_entry_points);
+    object_layers
+        .loader_systems
+        .insert(ENTRY_POINT_LAYER.to_string(), load_entry_points_system);
+    info!("Registered system for layer: {}", ENTRY_POINT_LAYER);
+}
+
+/// Reads the entry points and moves the player to the one it is arriving at
+fn load_entry_points(
+    object_layers: Res<ObjectLayers>,
+    level_data: Res<LevelData>,
+    mut entry_points: ResMut<EntryPoints>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut player_query: Query<(&mut Transform, &mut Velocity), With<Player>>,
+) {
+    entry_points.points.clear();
+    let Some(objects) = object_layers.layer_data.get(ENTRY_POINT_LAYER) else {
+        return;
+    };
+    for object in objects {
+        entry_points.points.insert(
+            object.name.clone(),
+            level_data.geometry.object_to_world(Vec2::new(object.x, object.y)),
+        );
+    }
+    info!("Loaded {} entry points", entry_points.points.len());
+
+    let Some(entry) = current_level.entry.take() else {
+        return;
+    };
+    let Some(position) = entry_points.points.get(&entry) else {
+        warn!("Entry point '{}' not found in {}", entry, current_level.map);
+        return;
+    };
+    if let Ok((mut transform, mut velocity)) = player_query.single_mut() {
+        info!("Player arriving at '{}' ({}, {})", entry, position.x, position.y);
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        *velocity = Velocity::zero();
+    }
+}
+
+/// Sends the player through doors: sensor zones with `target_map` and `target_entry`
+fn door_system(
+    mut enter_events: EventReader<EnterZoneEvent>,
+    mut transition_events: EventWriter<LevelTransitionEvent>,
+    mut current_level: ResMut<CurrentLevel>,
+    player_query: Query<(), With<Player>>,
+    time: Res<Time>,
+) {
+    current_level.arrival_timer.tick(time.delta());
+
+    for event in enter_events.read() {
+        if player_query.get(event.entity).is_err() {
+            continue;
+        }
+        let Some(PropertyValue::StringValue(map)) = event.properties.get("target_map") else {
+            continue;
+        };
+        if !current_level.arrival_timer.finished() {
+            debug!("Ignoring door '{}' right after arriving", event.name);
+            continue;
+        }
+        let entry = match event.properties.get("target_entry") {
+            Some(PropertyValue::StringValue(entry)) => entry.clone(),
+            _ => DEFAULT_ENTRY_POINT.to_string(),
+        };
+
+        info!("Player entered door '{}' to {} ({})", event.name, map, entry);
+        transition_events.write(LevelTransitionEvent {
+            map: map.clone(),
+            entry: Some(entry),
+        });
+    }
+}
+
+/// Unloads the current map's units and swaps the map handle; the map loader cleans up
+/// the old layers once the new map is ready
+fn level_transition_system(
+    mut commands: Commands,
+    mut transition_events: EventReader<LevelTransitionEvent>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut map_query: Query<(&mut TiledMapHandle, &mut TiledMapLoadState)>,
+    level_entity_query: Query<Entity, (With<LevelEntity>, Without<Player>)>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(event) = transition_events.read().last().cloned() else {
+        return;
+    };
+    let Ok((mut map_handle, mut load_state)) = map_query.single_mut() else {
+        error!("No map entity to load {} into", event.map);
+        return;
+    };
+
+    info!("Leaving {} for {}", current_level.map, event.map);
+    for entity in level_entity_query.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    map_handle.0 = asset_server.load(event.map.clone());
+    load_state.load_flag = false;
+    load_state.hot_reload = false;
+    current_level.map = event.map;
+    current_level.entry = event.entry;
+    current_level.arrival_timer.reset();
+}
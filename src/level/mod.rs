@@ -1,3 +1,8 @@
 pub mod level;
 pub mod tiled;
-pub mod helper;
\ No newline at end of file
+pub mod helper;
+pub mod collision;
+pub mod zone;
+pub mod chunks;
+pub mod layers;
+pub mod coordinates;
@@ -1,22 +1,33 @@
-use std::io::{Cursor, ErrorKind};
-use std::path::Path;
+use std::io::Cursor;
+use std::path::{Component as PathComponent, Path, PathBuf};
 use std::sync::Arc;
 
 use bevy::ecs::resource::Resource;
 use bevy::ecs::system::{ResMut, SystemId};
-use bevy::log::{info, error};
+use bevy::log::{debug, info, error};
 use bevy::{
-    asset::{AssetLoader, io::Reader},
-    platform::collections::HashMap,
+    asset::{AssetLoader, AssetPath, LoadContext, io::Reader},
+    platform::collections::{HashMap, HashSet},
     prelude::{
-        Asset, AssetApp, Assets, Bundle, Commands, Component, Entity, GlobalTransform, Handle,
-        Image, Plugin, Query, Res, Transform, Update,
+        Asset, AssetApp, AssetEvent, Assets, Bundle, Color, Commands, Component, Entity, EventReader,
+        GlobalTransform, Handle, Image, IntoScheduleConfigs, Name, Plugin, Query, Res, Transform,
+        Update, Visibility,
     },
     reflect::TypePath,
 };
+use bevy::color::Alpha;
+use bevy::math::{UVec2, Vec2};
 use bevy_ecs_tilemap::prelude::*;
 use log::warn;
 use tiled::ObjectData;
+use bevy::sprite::{Anchor, Sprite};
+
+use crate::level::chunks::InfiniteLayer;
+use crate::level::collision::{TileCollision, spawn_tile_colliders, tile_collision};
+use crate::level::coordinates::MapGeometry;
+use crate::level::layers::{Parallax, flatten_layers, tile_animation};
+use crate::level::zone::spawn_object_colliders;
+use crate::constants::TILESET_Z_STEP;
 
 #[derive(Default)]
 pub struct TiledMapPlugin;
@@ -27,29 +38,49 @@ impl Plugin for TiledMapPlugin {
             .init_resource::<LevelData>()
             .init_resource::<ObjectLayers>()
             .register_asset_loader(TiledLoader)
-            .add_systems(Update, process_loaded_maps);
+            .add_systems(Update, (reload_modified_maps, process_loaded_maps).chain());
     }
 }
 #[derive(Resource, Default)]
 pub struct LevelData {
     pub map: Option<tiled::Map>,
+    pub geometry: MapGeometry,
 }
 
 #[derive(TypePath, Asset)]
 pub struct TiledMap {
     pub map: tiled::Map,
     pub tilemap_textures: HashMap<usize, TilemapTexture>,
+    /// Per-tile images of image-collection tilesets, keyed by (tileset index, tile id)
+    pub tile_images: HashMap<(usize, u32), Handle<Image>>,
+    /// Images of image layers, keyed by layer id
+    pub layer_images: HashMap<u32, Handle<Image>>,
 }
 
 #[derive(Resource, Default)]
 pub struct ObjectLayers {
     pub layer_data: HashMap<String, Vec<ObjectData>>,
     pub loader_systems: HashMap<String, SystemId>,
+    /// On hot reload, the object ids each layer had before the map changed on disk
+    pub previous_ids: HashMap<String, HashSet<u32>>,
+}
+
+impl ObjectLayers {
+    /// Whether the object was added by a hot reload, or the map was loaded normally.
+    ///
+    /// Loaders that spawn units use this to leave the units of existing objects alone.
+    pub fn is_new_object(&self, layer: &str, id: u32) -> bool {
+        self.previous_ids
+            .get(layer)
+            .is_none_or(|ids| !ids.contains(&id))
+    }
 }
 
 #[derive(Component, Default)]
 pub struct TiledLayersStorage {
     pub storage: HashMap<u32, Entity>,
+    /// Colliders and zones spawned from object layers
+    pub objects: Vec<Entity>,
 }
 
 #[derive(Component, Default)]
@@ -58,6 +89,8 @@ pub struct TiledMapHandle(pub Handle<TiledMap>);
 #[derive(Component, Default)]
 pub struct TiledMapLoadState {
     pub load_flag: bool,
+    /// The same map changed on disk: object-layer systems only re-run for layers that changed
+    pub hot_reload: bool,
 }
 #[derive(Default, Bundle)]
 pub struct TiledMapBundle {
@@ -69,46 +102,28 @@ pub struct TiledMapBundle {
     pub render_settings: TilemapRenderSettings,
 }
 
-struct FilesystemResourceReader {
-    assets_dir: std::path::PathBuf,
+/// Serves the map and every other file tiled asks for (tilesets, templates) from bytes
+/// read beforehand through the asset source, so they are tracked as dependencies and hot
+/// reload the map when they change. tiled reads synchronously, so a file that was not read
+/// yet is recorded in `missing` and fails the load, which is retried once it is read.
+struct PrefetchedResourceReader<'a> {
+    files: &'a HashMap<PathBuf, Arc<[u8]>>,
+    missing: &'a mut Vec<PathBuf>,
 }
 
-impl FilesystemResourceReader {
-    fn new() -> Self {
-        Self {
-            assets_dir: std::path::PathBuf::from("assets"),
-        }
-    }
-}
-
-impl tiled::ResourceReader for FilesystemResourceReader {
-    type Resource = std::fs::File;
-    type Error = std::io::Error;
+impl tiled::ResourceReader for PrefetchedResourceReader<'_> {
+    type Resource = Cursor<Arc<[u8]>>;
+    type Error = TiledAssetLoaderError;
 
     fn read_from(&mut self, path: &Path) -> std::result::Result<Self::Resource, Self::Error> {
-        info!("Resource reader attempting to load: {:?}", path);
-
-        let full_path = if path.is_absolute() {
-            // Absolute path - use as is
-            path.to_path_buf()
-        } else if path.starts_with("assets") {
-            // Already prefixed with assets - use as is
-            path.to_path_buf()
-        } else {
-            // Relative path - resolve relative to assets/map/ (where our TMX file is)
-            self.assets_dir.join("map").join(path)
-        };
-
-        info!("Resolved file path: {:?}", full_path);
-
-        match std::fs::File::open(&full_path) {
-            Ok(file) => {
-                info!("Successfully opened file: {:?}", full_path);
-                Ok(file)
-            },
-            Err(e) => {
-                error!("Failed to open file {:?}: {}", full_path, e);
-                Err(e)
+        match self.files.get(path) {
+            Some(bytes) => Ok(Cursor::new(bytes.clone())),
+            None => {
+                self.missing.push(path.to_path_buf());
+                Err(TiledAssetLoaderError::Dependency(
+                    path.to_path_buf(),
+                    "not read yet".to_string(),
+                ))
             }
         }
     }
@@ -119,6 +134,16 @@ pub struct TiledLoader;
 #[derive(Debug)]
 pub enum TiledAssetLoaderError {
     Io(std::io::Error),
+    /// A tileset file referenced by the map could not be read
+    MissingTileset(PathBuf, String),
+    /// A file other than a tileset (e.g. an object template) could not be read
+    Dependency(PathBuf, String),
+    /// A path points outside the asset source
+    BadPath(PathBuf),
+    /// An image referenced by a tileset or image layer has an unusable path
+    BadImagePath(PathBuf),
+    /// The map or one of its files is not valid Tiled XML
+    Parse(String),
 }
 
 impl From<std::io::Error> for TiledAssetLoaderError {
@@ -127,10 +152,39 @@ impl From<std::io::Error> for TiledAssetLoaderError {
     }
 }
 
+impl From<tiled::Error> for TiledAssetLoaderError {
+    fn from(err: tiled::Error) -> Self {
+        match err {
+            tiled::Error::ResourceLoadingError { path, err }
+                if path.extension().is_some_and(|extension| extension == "tsx") =>
+            {
+                TiledAssetLoaderError::MissingTileset(path, err.to_string())
+            }
+            tiled::Error::ResourceLoadingError { path, err } => {
+                TiledAssetLoaderError::Dependency(path, err.to_string())
+            }
+            err => TiledAssetLoaderError::Parse(err.to_string()),
+        }
+    }
+}
+
 impl std::fmt::Display for TiledAssetLoaderError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             TiledAssetLoaderError::Io(err) => write!(f, "Could not load Tiled file: {}", err),
+            TiledAssetLoaderError::MissingTileset(path, err) => {
+                write!(f, "Could not load tileset {}: {}", path.display(), err)
+            }
+            TiledAssetLoaderError::Dependency(path, err) => {
+                write!(f, "Could not load {}: {}", path.display(), err)
+            }
+            TiledAssetLoaderError::BadPath(path) => {
+                write!(f, "Path {} is outside the asset source", path.display())
+            }
+            TiledAssetLoaderError::BadImagePath(path) => {
+                write!(f, "Bad image path {}", path.display())
+            }
+            TiledAssetLoaderError::Parse(err) => write!(f, "Could not parse Tiled map: {}", err),
         }
     }
 }
@@ -146,33 +200,42 @@ impl AssetLoader for TiledLoader {
         &self,
         reader: &mut dyn Reader,
         _settings: &Self::Settings,
-        load_context: &mut bevy::asset::LoadContext<'_>,
+        load_context: &mut LoadContext<'_>,
     ) -> Result<Self::Asset, Self::Error> {
         let mut bytes = Vec::new();
         reader.read_to_end(&mut bytes).await?;
 
-        // Create a filesystem resource reader that knows about the current map's directory
-        let map_dir = if let Some(parent) = load_context.path().parent() {
-            std::path::PathBuf::from("assets").join(parent)
-        } else {
-            std::path::PathBuf::from("assets")
+        // Paths tiled hands back are relative to the asset source root, like `map/wall.tsx`
+        let map_path = load_context.path().to_path_buf();
+        let mut files = HashMap::default();
+        files.insert(map_path.clone(), Arc::from(bytes));
+        let map = loop {
+            let mut missing = Vec::new();
+            let result = tiled::Loader::with_cache_and_reader(
+                tiled::DefaultResourceCache::new(),
+                PrefetchedResourceReader {
+                    files: &files,
+                    missing: &mut missing,
+                },
+            )
+            .load_tmx_map(&map_path);
+            match result {
+                Ok(map) => break map,
+                Err(err) if missing.is_empty() => {
+                    error!("Could not load TMX map {:?}: {}", map_path, err);
+                    return Err(TiledAssetLoaderError::from(err));
+                }
+                // tiled stopped at a file that was not read yet
+                Err(_) => {}
+            }
+            for path in missing {
+                let bytes = read_dependency(load_context, &path).await?;
+                files.insert(path, bytes);
+            }
         };
 
-        info!("Map directory: {:?}", map_dir);
-
-        let mut loader = tiled::Loader::with_cache_and_reader(
-            tiled::DefaultResourceCache::new(),
-            FilesystemResourceReader::new(),
-        );
-
-        // Load from the full filesystem path
-        let map_path = std::path::PathBuf::from("assets").join(load_context.path());
-        let map = loader.load_tmx_map(&map_path).map_err(|e| {
-            error!("Could not load TMX map from {:?}: {}", map_path, e);
-            std::io::Error::new(ErrorKind::Other, format!("Could not load TMX map: {e}"))
-        })?;
-
         let mut tilemap_textures = HashMap::default();
+        let mut tile_images = HashMap::default();
 
         for (tileset_index, tileset) in map.tilesets().iter().enumerate() {
             info!("Processing tileset {}: name='{}', image={:?}",
@@ -183,25 +246,19 @@ impl AssetLoader for TiledLoader {
 
             let tilemap_texture = match &tileset.image {
                 None => {
-                    info!("Skipping image collection tileset '{}' (no image)", tileset.name);
+                    // Image collection: load each tile's own image
+                    for (tile_id, tile) in tileset.tiles() {
+                        if let Some(img) = &tile.image {
+                            let texture = load_image(load_context, &img.source)?;
+                            tile_images.insert((tileset_index, tile_id), texture);
+                        }
+                    }
+                    info!("Loaded image collection tileset '{}'", tileset.name);
                     continue;
                 }
                 Some(img) => {
-                    let texture_path = img.source.to_str().unwrap();
-                    info!("Loading texture from path: {}", texture_path);
-
-                    // Handle relative paths in tileset images
-                    let resolved_path = if texture_path.starts_with("../") {
-                        // Remove the ../ prefix and use the path relative to assets
-                        texture_path.strip_prefix("../").unwrap_or(texture_path)
-                    } else {
-                        texture_path
-                    };
-
-                    info!("Resolved texture path: {}", resolved_path);
-                    let texture: Handle<Image> = load_context.load(resolved_path);
-
-                    TilemapTexture::Single(texture.clone())
+                    info!("Loading texture from path: {:?}", img.source);
+                    TilemapTexture::Single(load_image(load_context, &img.source)?)
                 }
             };
 
@@ -209,9 +266,21 @@ impl AssetLoader for TiledLoader {
             info!("Added tilemap texture for tileset {}", tileset_index);
         }
 
+        let mut layer_images = HashMap::default();
+        for flat_layer in flatten_layers(map.layers()) {
+            if let tiled::LayerType::Image(image_layer) = flat_layer.layer.layer_type() {
+                if let Some(img) = &image_layer.image {
+                    let texture = load_image(load_context, &img.source)?;
+                    layer_images.insert(flat_layer.layer.id(), texture);
+                }
+            }
+        }
+
         let asset_map = TiledMap {
             map,
             tilemap_textures,
+            tile_images,
+            layer_images,
         };
 
         info!("Loaded map: {}", load_context.path().display());
@@ -224,10 +293,89 @@ impl AssetLoader for TiledLoader {
     }
 }
 
+/// Reads a tileset or template referenced by the map from the same asset source as the map
+async fn read_dependency(
+    load_context: &mut LoadContext<'_>,
+    path: &Path,
+) -> Result<Arc<[u8]>, TiledAssetLoaderError> {
+    let asset_path = normalize_asset_path(path)
+        .ok_or_else(|| TiledAssetLoaderError::BadPath(path.to_path_buf()))?;
+    debug!("Reading map dependency {}", asset_path);
+    let source = load_context.asset_path().source().clone_owned();
+    let bytes = load_context
+        .read_asset_bytes(AssetPath::from(asset_path).with_source(source))
+        .await
+        .map_err(|err| {
+            error!("Could not read map dependency {:?}: {}", path, err);
+            if path.extension().is_some_and(|extension| extension == "tsx") {
+                TiledAssetLoaderError::MissingTileset(path.to_path_buf(), err.to_string())
+            } else {
+                TiledAssetLoaderError::Dependency(path.to_path_buf(), err.to_string())
+            }
+        })?;
+    Ok(Arc::from(bytes))
+}
+
+/// Loads an image referenced by the map from the same asset source as the map
+fn load_image(load_context: &mut LoadContext, path: &Path) -> Result<Handle<Image>, TiledAssetLoaderError> {
+    let asset_path = normalize_asset_path(path)
+        .ok_or_else(|| TiledAssetLoaderError::BadImagePath(path.to_path_buf()))?;
+    let source = load_context.asset_path().source().clone_owned();
+    Ok(load_context.load(AssetPath::from(asset_path).with_source(source)))
+}
+
+/// Resolves `..` in a path tiled built by joining a relative reference onto the
+/// referencing file's directory, e.g. `map/../tileset/Tileset.png` becomes
+/// `tileset/Tileset.png`. Returns `None` for paths leaving the asset source.
+fn normalize_asset_path(path: &Path) -> Option<String> {
+    let mut components: Vec<&std::ffi::OsStr> = Vec::new();
+    for component in path.components() {
+        match component {
+            PathComponent::ParentDir => {
+                components.pop()?;
+            }
+            PathComponent::Normal(part) => components.push(part),
+            PathComponent::CurDir => {}
+            PathComponent::RootDir | PathComponent::Prefix(_) => return None,
+        }
+    }
+    if components.is_empty() {
+        return None;
+    }
+    Some(
+        components
+            .iter()
+            .map(|part| part.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+/// Rebuilds the current map in place when its file or one of its tilesets changes on disk.
+///
+/// Only tiles, colliders and zones are rebuilt; units and other gameplay state stay as they are.
+pub fn reload_modified_maps(
+    mut asset_events: EventReader<AssetEvent<TiledMap>>,
+    mut map_query: Query<(&TiledMapHandle, &mut TiledMapLoadState)>,
+) {
+    for event in asset_events.read() {
+        let AssetEvent::Modified { id } = event else {
+            continue;
+        };
+        for (map_handle, mut load_state) in map_query.iter_mut() {
+            // Maps that haven't been built yet will pick up the new version anyway
+            if map_handle.0.id() == *id && load_state.load_flag {
+                info!("Map {:?} changed, reloading", id);
+                load_state.load_flag = false;
+                load_state.hot_reload = true;
+            }
+        }
+    }
+}
+
 pub fn process_loaded_maps(
     mut commands: Commands,
     maps: Res<Assets<TiledMap>>,
-    tile_storage_query: Query<(Entity, &TileStorage)>,
     mut map_query: Query<(
         &TiledMapHandle,
         &mut TiledMapLoadState,
@@ -245,87 +393,158 @@ pub fn process_loaded_maps(
         }
         if let Some(tiled_map) = maps.get(&map_handle.0) {
             level_data.map = Some(tiled_map.map.clone());
+            level_data.geometry = MapGeometry::from_map(&tiled_map.map);
             load_state.load_flag = true;
+            let hot_reload = std::mem::take(&mut load_state.hot_reload);
             
-            // Clean up existing layers
-            for layer_entity in layer_storage.storage.values() {
-                if let Ok((_, layer_tile_storage)) = tile_storage_query.get(*layer_entity) {
-                    for tile in layer_tile_storage.iter().flatten() {
-                        commands.entity(*tile).despawn()
-                    }
+            // Clean up existing layers; tilemaps, tiles and colliders are children
+            for (_, layer_entity) in layer_storage.storage.drain() {
+                commands.entity(layer_entity).despawn();
+            }
+            for object_entity in layer_storage.objects.drain(..) {
+                commands.entity(object_entity).despawn();
+            }
+            // Object data from the previous map must not leak into the new one; on hot reload
+            // it is kept aside to find the layers that changed
+            let previous_layer_data = std::mem::take(&mut object_layers.layer_data);
+            object_layers.previous_ids.clear();
+            if hot_reload {
+                for (name, objects) in previous_layer_data.iter() {
+                    let ids = objects.iter().map(|object| object.id()).collect();
+                    object_layers.previous_ids.insert(name.clone(), ids);
                 }
-                commands.entity(*layer_entity).despawn();
             }
             
             // Process layers
-            for (layer_index, layer) in tiled_map.map.layers().enumerate() {
-                let offset_x = layer.offset_x;
-                let offset_y = layer.offset_y;
+            // Group layers are flattened, their children keep the group's offset, opacity and parallax
+            for (layer_index, flat_layer) in flatten_layers(tiled_map.map.layers()).into_iter().enumerate() {
+                let layer = flat_layer.layer;
+                let offset_x = flat_layer.offset.x;
+                let offset_y = flat_layer.offset.y;
+                let layer_position = Vec2::new(offset_x, -offset_y);
+                let layer_visibility = if flat_layer.visible {
+                    Visibility::Inherited
+                } else {
+                    Visibility::Hidden
+                };
+                let tile_color = TileColor(Color::WHITE.with_alpha(flat_layer.opacity));
                 
                 match layer.layer_type() {
                     tiled::LayerType::Tiles(tile_layer) => {
                         if let tiled::TileLayer::Finite(layer_data) = tile_layer {
-                            let map_size = TilemapSize {
-                                x: tiled_map.map.width,
-                                y: tiled_map.map.height,
-                            };
+                            let geometry = level_data.geometry;
+                            let map_size = geometry.tilemap_size();
                             let grid_size = TilemapGridSize {
-                                x: tiled_map.map.tile_width as f32,
-                                y: tiled_map.map.tile_height as f32,
-                            };
-                            let map_type = match tiled_map.map.orientation {
-                                tiled::Orientation::Hexagonal => {
-                                    TilemapType::Hexagon(HexCoordSystem::Row)
-                                }
-                                tiled::Orientation::Isometric => {
-                                    TilemapType::Isometric(IsoCoordSystem::Diamond)
-                                }
-                                tiled::Orientation::Staggered => {
-                                    TilemapType::Isometric(IsoCoordSystem::Staggered)
-                                }
-                                tiled::Orientation::Orthogonal => TilemapType::Square,
+                                x: geometry.tile_size.x,
+                                y: geometry.tile_size.y,
                             };
+                            let map_type = geometry.tilemap_type();
                             
-                            let mut tile_storage = TileStorage::empty(map_size);
-                            let layer_entity = commands.spawn_empty().id();
+                            let layer_entity = commands
+                                .spawn((
+                                    Transform::from_translation(layer_position.extend(layer_index as f32)),
+                                    layer_visibility,
+                                    Name::new(format!("Layer {}", layer.name)),
+                                ))
+                                .id();
+                            if let Some(parallax) = Parallax::new(flat_layer.parallax, layer_position) {
+                                commands.entity(layer_entity).insert(parallax);
+                            }
+
+                            // Collision is only generated for orthogonal maps
+                            let generate_collision = matches!(map_type, TilemapType::Square);
+                            let mut solid_tiles = vec![false; (geometry.width * geometry.height) as usize];
+                            let mut custom_shapes = Vec::new();
                             
                             // Group tiles by tileset to process them together
                             let mut tileset_tiles: HashMap<usize, Vec<_>> = HashMap::new();
                             
-                            for x in 0..map_size.x {
-                                for y in 0..map_size.y {
-                                    let mapped_y = (tiled_map.map.height - 1 - y) as i32;
-                                    let mapped_x = x as i32;
-                                    
-                                    let layer_tile = match layer_data.get_tile(mapped_x, mapped_y) {
+                            for tiled_x in 0..geometry.width {
+                                for tiled_y in 0..geometry.height {
+                                    let layer_tile = match layer_data.get_tile(tiled_x as i32, tiled_y as i32) {
                                         Some(t) => t,
                                         None => continue,
                                     };
                                     
-                                    let layer_tile_data = match layer_data.get_tile_data(mapped_x, mapped_y) {
+                                    let layer_tile_data = match layer_data.get_tile_data(tiled_x as i32, tiled_y as i32) {
                                         Some(d) => d,
                                         None => continue,
                                     };
+                                    let tile_pos = geometry.tile_pos(tiled_x, tiled_y);
+                                    let (x, y) = (tile_pos.x, tile_pos.y);
                                     
                                     let tileset_index = layer_tile.tileset_index();
+                                    if generate_collision {
+                                        let tileset = &tiled_map.map.tilesets()[tileset_index];
+                                        let collision = layer_tile.get_tile().and_then(|tile| {
+                                            tile_collision(
+                                                &tile,
+                                                layer_tile.id(),
+                                                tileset.tile_width as f32,
+                                                tileset.tile_height as f32,
+                                            )
+                                        });
+                                        match collision {
+                                            Some(TileCollision::Full) => {
+                                                solid_tiles[(y * geometry.width + x) as usize] = true;
+                                            }
+                                            Some(TileCollision::Shapes(shapes)) => {
+                                                let tile_origin = Vec2::new(
+                                                    x as f32 * grid_size.x,
+                                                    y as f32 * grid_size.y,
+                                                );
+                                                custom_shapes.extend(shapes.into_iter().map(
+                                                    |(position, collider)| (tile_origin + position, collider),
+                                                ));
+                                            }
+                                            None => {}
+                                        }
+                                    }
+
                                     tileset_tiles.entry(tileset_index).or_insert_with(Vec::new).push((
-                                        x, y, layer_tile, layer_tile_data
+                                        tiled_x, tiled_y, tile_pos, layer_tile, layer_tile_data
                                     ));
                                 }
                             }
                             
-                            // Process tiles for each tileset that has a texture
+                            // Each tileset used by the layer gets its own tilemap under the layer,
+                            // ordered by tileset index so overlapping tiles draw deterministically
                             for (tileset_index, tiles) in tileset_tiles {
-                                // Skip tilesets that don't have textures (e.g., image collections)
-                                let tilemap_texture = match tiled_map.tilemap_textures.get(&tileset_index) {
-                                    Some(texture) => texture,
-                                    None => {
-                                        warn!("Skipping tiles from tileset {} (no texture available)", tileset_index);
-                                        continue;
+                                let tileset = &tiled_map.map.tilesets()[tileset_index];
+                                let tileset_z = tileset_index as f32 * TILESET_Z_STEP;
+
+                                let Some(tilemap_texture) = tiled_map.tilemap_textures.get(&tileset_index) else {
+                                    // Image collection: every tile has its own image, draw them as sprites
+                                    for (tiled_x, tiled_y, _, layer_tile, layer_tile_data) in tiles {
+                                        let Some(image) = tiled_map.tile_images.get(&(tileset_index, layer_tile.id())) else {
+                                            warn!("No image for tile {} in tileset '{}'", layer_tile.id(), tileset.name);
+                                            continue;
+                                        };
+                                        let sprite = commands
+                                            .spawn((
+                                                Sprite {
+                                                    image: image.clone(),
+                                                    color: tile_color.0,
+                                                    flip_x: layer_tile_data.flip_h,
+                                                    flip_y: layer_tile_data.flip_v,
+                                                    anchor: Anchor::BottomLeft,
+                                                    ..Default::default()
+                                                },
+                                                // Bottom-left of the tile's cell, like Tiled draws them
+                                                Transform::from_translation(
+                                                    (geometry.tile_center(tiled_x as i32, tiled_y as i32)
+                                                        - geometry.tile_size / 2.0)
+                                                        .extend(tileset_z),
+                                                ),
+                                            ))
+                                            .id();
+                                        commands.entity(layer_entity).add_child(sprite);
                                     }
+                                    continue;
                                 };
-                                
-                                let tileset = &tiled_map.map.tilesets()[tileset_index];
+
+                                let tilemap_entity = commands.spawn_empty().id();
+                                let mut tile_storage = TileStorage::empty(map_size);
                                 let tile_size = TilemapTileSize {
                                     x: tileset.tile_width as f32,
                                     y: tileset.tile_height as f32,
@@ -334,69 +553,148 @@ pub fn process_loaded_maps(
                                     x: tileset.spacing as f32,
                                     y: tileset.spacing as f32,
                                 };
-                                
-                                for (x, y, layer_tile, layer_tile_data) in tiles {
+
+                                for (_, _, tile_pos, layer_tile, layer_tile_data) in tiles {
                                     let texture_index = match tilemap_texture {
                                         TilemapTexture::Single(_) => layer_tile.id(),
                                     };
-                                    
-                                    let tile_pos = TilePos { x, y };
+
                                     let tile_entity = commands
                                         .spawn(TileBundle {
                                             position: tile_pos,
-                                            tilemap_id: TilemapId(layer_entity),
+                                            tilemap_id: TilemapId(tilemap_entity),
                                             texture_index: TileTextureIndex(texture_index),
                                             flip: TileFlip {
                                                 x: layer_tile_data.flip_h,
                                                 y: layer_tile_data.flip_v,
                                                 d: layer_tile_data.flip_d,
                                             },
+                                            color: tile_color,
                                             ..Default::default()
                                         })
                                         .id();
+                                    if let Some(animation) = layer_tile.get_tile().and_then(|tile| tile_animation(&tile)) {
+                                        commands.entity(tile_entity).insert(animation);
+                                    }
                                     tile_storage.set(&tile_pos, tile_entity);
+                                    // Parent tiles so despawning the layer takes them along
+                                    commands.entity(tilemap_entity).add_child(tile_entity);
                                 }
-                                
-                                // Create the tilemap bundle for this tileset
-                                // Note: This creates multiple tilemaps per layer if multiple tilesets are used
-                                // You might want to handle this differently depending on your needs
-                                commands.entity(layer_entity).insert(TilemapBundle {
-                                    grid_size,
-                                    size: map_size,
-                                    storage: tile_storage.clone(),
-                                    texture: tilemap_texture.clone(),
-                                    tile_size,
-                                    spacing: tile_spacing,
-                                    anchor: TilemapAnchor::BottomLeft,
-                                    transform: Transform::from_xyz(
-                                        offset_x,
-                                        -offset_y,
-                                        layer_index as f32,
-                                    ),
-                                    map_type,
-                                    render_settings: *render_settings,
-                                    ..Default::default()
-                                });
+
+                                commands.entity(tilemap_entity).insert((
+                                    TilemapBundle {
+                                        grid_size,
+                                        size: map_size,
+                                        storage: tile_storage,
+                                        texture: tilemap_texture.clone(),
+                                        tile_size,
+                                        spacing: tile_spacing,
+                                        anchor: TilemapAnchor::None,
+                                        transform: Transform::from_translation(
+                                            geometry.tilemap_origin().extend(tileset_z),
+                                        ),
+                                        map_type,
+                                        render_settings: *render_settings,
+                                        ..Default::default()
+                                    },
+                                    Name::new(format!("{} / {}", layer.name, tileset.name)),
+                                ));
+                                commands.entity(layer_entity).add_child(tilemap_entity);
                             }
-                            
+
+                            let collider_count = spawn_tile_colliders(
+                                &mut commands,
+                                layer_entity,
+                                &solid_tiles,
+                                custom_shapes,
+                                UVec2::new(geometry.width, geometry.height),
+                                geometry.tile_size,
+                                geometry.bottom_left(),
+                            );
+                            if collider_count > 0 {
+                                info!("Generated {} colliders for layer '{}'", collider_count, layer.name);
+                            }
+
+                            layer_storage.storage.insert(layer_index as u32, layer_entity);
+                        } else {
+                            // Infinite layers stream their chunks in around the camera
+                            let layer_entity = commands
+                                .spawn((
+                                    InfiniteLayer::new(layer.id(), map_handle.0.clone(), flat_layer.opacity),
+                                    Transform::from_translation(layer_position.extend(layer_index as f32)),
+                                    layer_visibility,
+                                    Name::new(format!("InfiniteLayer {}", layer.name)),
+                                ))
+                                .id();
+                            if let Some(parallax) = Parallax::new(flat_layer.parallax, layer_position) {
+                                commands.entity(layer_entity).insert(parallax);
+                            }
+                            info!("Layer '{}' is infinite, streaming chunks", layer.name);
                             layer_storage.storage.insert(layer_index as u32, layer_entity);
                         }
                     }
                     tiled::LayerType::Objects(object_layer) => {
                         let data: Vec<ObjectData> = object_layer.object_data().iter().cloned().collect();
-                        object_layers.layer_data.insert(layer.name.clone(), data);
                         info!("Loaded object layer '{}' with {} objects", layer.name, object_layer.object_data().len());
+
+                        let object_entities = spawn_object_colliders(&mut commands, &layer.name, &data, &level_data.geometry);
+                        layer_storage.objects.extend(object_entities);
+                        let unchanged = hot_reload && previous_layer_data.get(&layer.name) == Some(&data);
+                        object_layers.layer_data.insert(layer.name.clone(), data);
                         
                         // Run system if one is registered for this layer
                         if let Some(system) = object_layers.loader_systems.get(&layer.name) {
-                            commands.run_system(*system);
+                            if unchanged {
+                                debug!("Object layer '{}' unchanged, not reloading", layer.name);
+                            } else {
+                                commands.run_system(*system);
+                            }
+                        }
+                    }
+                    tiled::LayerType::Image(_) => {
+                        let Some(image) = tiled_map.layer_images.get(&layer.id()) else {
+                            warn!("Image layer '{}' has no image", layer.name);
+                            continue;
+                        };
+                        // Tiled places the image's top-left corner at the layer offset
+                        let layer_entity = commands
+                            .spawn((
+                                Sprite {
+                                    image: image.clone(),
+                                    color: Color::WHITE.with_alpha(flat_layer.opacity),
+                                    anchor: Anchor::TopLeft,
+                                    ..Default::default()
+                                },
+                                Transform::from_translation(layer_position.extend(layer_index as f32)),
+                                layer_visibility,
+                                Name::new(format!("ImageLayer {}", layer.name)),
+                            ))
+                            .id();
+                        if let Some(parallax) = Parallax::new(flat_layer.parallax, layer_position) {
+                            commands.entity(layer_entity).insert(parallax);
                         }
+                        info!("Loaded image layer '{}'", layer.name);
+                        layer_storage.storage.insert(layer_index as u32, layer_entity);
                     }
-                    _ => {
-                        warn!("Unsupported Layer {}", layer.id());
+                    tiled::LayerType::Group(_) => {
+                        // Already flattened into their children
                     }
                 }
             }
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_tileset_relative_paths() {
+        assert_eq!(
+            normalize_asset_path(Path::new("map/../tileset/Tileset.png")).as_deref(),
+            Some("tileset/Tileset.png")
+        );
+        assert_eq!(normalize_asset_path(Path::new("map/./wall.tsx")).as_deref(), Some("map/wall.tsx"));
+        assert_eq!(normalize_asset_path(Path::new("map/../../secret.png")), None);
+    }
+}
@@ -0,0 +1,166 @@
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use tiled::{ObjectData, PropertyValue};
+
+use crate::game_state::GameplaySet;
+use crate::level::collision::object_shape_collider;
+use crate::level::coordinates::MapGeometry;
+use crate::unit::Unit;
+
+/// Collider spawned from an object layer, carrying the object's name and properties
+#[derive(Component)]
+pub struct Zone {
+    pub name: String,
+    pub layer: String,
+    pub properties: tiled::Properties,
+}
+
+/// How an object's `collider` property turns it into physics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZoneKind {
+    /// Blocks movement like a wall
+    Solid,
+    /// Lets units through and reports `EnterZoneEvent`/`ExitZoneEvent`
+    Sensor,
+}
+
+impl ZoneKind {
+    pub fn from_properties(properties: &tiled::Properties) -> Option<Self> {
+        match properties.get("collider") {
+            Some(PropertyValue::StringValue(kind)) => match kind.as_str() {
+                "solid" => Some(Self::Solid),
+                "sensor" | "trigger" => Some(Self::Sensor),
+                other => {
+                    warn!("Unknown collider kind '{}'", other);
+                    None
+                }
+            },
+            _ => None,
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct EnterZoneEvent {
+    pub zone: Entity,
+    pub entity: Entity,
+    pub name: String,
+    pub properties: tiled::Properties,
+}
+
+#[derive(Event)]
+pub struct ExitZoneEvent {
+    pub zone: Entity,
+    pub entity: Entity,
+    pub name: String,
+    pub properties: tiled::Properties,
+}
+
+pub struct ZonePlugin;
+
+impl Plugin for ZonePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<EnterZoneEvent>()
+            .add_event::<ExitZoneEvent>()
+            .add_systems(Update, zone_events_system.in_set(GameplaySet));
+    }
+}
+
+/// Spawns colliders for every object in the layer that has a `collider` property.
+/// Returns the spawned entities so they can be cleaned up with the map.
+pub fn spawn_object_colliders(
+    commands: &mut Commands,
+    layer_name: &str,
+    objects: &[ObjectData],
+    geometry: &MapGeometry,
+) -> Vec<Entity> {
+    let mut entities = Vec::new();
+
+    for object in objects {
+        let Some(kind) = ZoneKind::from_properties(&object.properties) else {
+            continue;
+        };
+        let Some((offset, collider)) = object_shape_collider(&object.shape) else {
+            warn!(
+                "Object '{}' (id {}) in layer '{}' has no supported shape for a collider",
+                object.name,
+                object.id(),
+                layer_name
+            );
+            continue;
+        };
+
+        // Tiled rotates clockwise around the object origin
+        let position = geometry.object_to_world(Vec2::new(object.x, object.y));
+        let transform = Transform::from_translation(position.extend(0.0))
+            .with_rotation(Quat::from_rotation_z(-object.rotation.to_radians()));
+        let mut entity = commands.spawn((
+            Zone {
+                name: object.name.clone(),
+                layer: layer_name.to_string(),
+                properties: object.properties.clone(),
+            },
+            RigidBody::Fixed,
+            Collider::compound(vec![(offset, 0.0, collider)]),
+            transform,
+            Name::new(format!("Zone {}", object.name)),
+        ));
+        if kind == ZoneKind::Sensor {
+            entity.insert((Sensor, ActiveEvents::COLLISION_EVENTS));
+        }
+
+        debug!(
+            "Spawned {:?} zone '{}' (id {}) from layer '{}'",
+            kind,
+            object.name,
+            object.id(),
+            layer_name
+        );
+        entities.push(entity.id());
+    }
+
+    entities
+}
+
+/// Turns sensor contacts between zones and units into enter/exit events
+fn zone_events_system(
+    mut collision_events: EventReader<CollisionEvent>,
+    zone_query: Query<&Zone>,
+    unit_query: Query<(), With<Unit>>,
+    mut enter_events: EventWriter<EnterZoneEvent>,
+    mut exit_events: EventWriter<ExitZoneEvent>,
+) {
+    for collision_event in collision_events.read() {
+        let (entity1, entity2, started) = match collision_event {
+            CollisionEvent::Started(entity1, entity2, _) => (*entity1, *entity2, true),
+            CollisionEvent::Stopped(entity1, entity2, _) => (*entity1, *entity2, false),
+        };
+
+        let (zone_entity, zone, entity) = match (zone_query.get(entity1), zone_query.get(entity2)) {
+            (Ok(zone), Err(_)) => (entity1, zone, entity2),
+            (Err(_), Ok(zone)) => (entity2, zone, entity1),
+            _ => continue,
+        };
+        if unit_query.get(entity).is_err() {
+            continue;
+        }
+
+        if started {
+            info!("Entity {:?} entered zone '{}'", entity, zone.name);
+            enter_events.write(EnterZoneEvent {
+                zone: zone_entity,
+                entity,
+                name: zone.name.clone(),
+                properties: zone.properties.clone(),
+            });
+        } else {
+            info!("Entity {:?} left zone '{}'", entity, zone.name);
+            exit_events.write(ExitZoneEvent {
+                zone: zone_entity,
+                entity,
+                name: zone.name.clone(),
+                properties: zone.properties.clone(),
+            });
+        }
+    }
+}
@@ -0,0 +1,394 @@
+use crate::Player;
+use crate::constants::*;
+use crate::float_text::{FloatingTextConfig, spawn_floating_text};
+use crate::game_state::GameplaySet;
+use crate::level::level::LevelEntity;
+use crate::stats::{ModifierSource, StatKind, StatModifier, Stats};
+use crate::unit::{HpChangeEvent, Unit};
+use crate::unit_death::UnitDiedEvent;
+use crate::unit_template::index_folder;
+use bevy::asset::{AssetLoader, LoadContext, LoadedFolder, io::Reader};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+/// What a unit drops, loaded from `assets/loot/<id>.loot.ron`
+#[derive(Asset, TypePath, Deserialize, Clone, Debug)]
+pub struct LootTable {
+    /// Gold dropped, between min and max inclusive
+    #[serde(default)]
+    pub gold: Option<(u32, u32)>,
+    /// Always dropped
+    #[serde(default)]
+    pub guaranteed: Vec<Item>,
+    /// Weighted picks from `entries`
+    #[serde(default = "default_rolls")]
+    pub rolls: u32,
+    #[serde(default)]
+    pub entries: Vec<LootEntry>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct LootEntry {
+    /// `None` makes a roll drop nothing
+    pub item: Option<Item>,
+    pub weight: u32,
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub enum Item {
+    Gold(u32),
+    /// Heals this much HP
+    HealthPotion(f32),
+    /// Percent-add modifier on a stat for a while, e.g. 0.2 for +20%
+    Tonic {
+        stat: StatKind,
+        percent: f32,
+        seconds: f32,
+    },
+}
+
+fn default_rolls() -> u32 {
+    1
+}
+
+impl LootTable {
+    pub fn roll(&self, rng: &mut impl Rng) -> Vec<Item> {
+        let mut drops = self.guaranteed.clone();
+        if let Some((min, max)) = self.gold {
+            let gold = rng.gen_range(min..=max.max(min));
+            if gold > 0 {
+                drops.push(Item::Gold(gold));
+            }
+        }
+
+        let total: u32 = self.entries.iter().map(|entry| entry.weight).sum();
+        if total == 0 {
+            return drops;
+        }
+        for _ in 0..self.rolls {
+            let mut pick = rng.gen_range(0..total);
+            for entry in &self.entries {
+                if pick < entry.weight {
+                    drops.extend(entry.item.clone());
+                    break;
+                }
+                pick -= entry.weight;
+            }
+        }
+        drops
+    }
+}
+
+impl Item {
+    fn color(&self) -> Color {
+        match self {
+            Item::Gold(_) => Color::srgb(1.5, 1.2, 0.2),
+            Item::HealthPotion(_) => Color::srgb(1.5, 0.2, 0.3),
+            Item::Tonic { .. } => Color::srgb(0.3, 0.6, 1.5),
+        }
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Item::Gold(gold) => format!("+{} gold", gold),
+            Item::HealthPotion(hp) => format!("+{:.0} HP", hp),
+            Item::Tonic { stat, percent, .. } => format!("+{:.0}% {:?}", percent * 100.0, stat),
+        }
+    }
+}
+
+/// Loot table handles keyed by table id, the file name without `.loot.ron`
+#[derive(Resource, Default)]
+pub struct LootTables {
+    pub tables: HashMap<String, Handle<LootTable>>,
+    /// Every table in `assets/loot`, indexed into `tables` once it has loaded
+    folder: Handle<LoadedFolder>,
+}
+
+/// Loot table id rolled when the unit dies
+#[derive(Component, Debug, Clone)]
+pub struct Loot(pub String);
+
+/// Item lying on the ground, collected when the player touches its sensor
+#[derive(Component, Debug, Clone)]
+pub struct Pickup {
+    pub item: Item,
+}
+
+/// Gold collected by the player
+#[derive(Resource, Default)]
+pub struct Purse {
+    pub gold: u32,
+}
+
+#[derive(Component)]
+struct GoldText;
+
+pub struct LootPlugin;
+
+impl Plugin for LootPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<LootTable>()
+            .register_asset_loader(LootTableLoader)
+            .init_resource::<LootTables>()
+            .init_resource::<Purse>()
+            .add_systems(Startup, (load_loot_tables, setup_gold_ui))
+            .add_systems(
+                Update,
+                (
+                    index_loot_tables,
+                    (drop_loot, attract_pickups, collect_pickups)
+                        .chain()
+                        .in_set(GameplaySet),
+                    update_gold_ui,
+                ),
+            );
+    }
+}
+
+fn load_loot_tables(mut tables: ResMut<LootTables>, asset_server: Res<AssetServer>) {
+    tables.folder = asset_server.load_folder(LOOT_TABLE_DIR);
+    info!("Loading loot tables from '{}'", LOOT_TABLE_DIR);
+}
+
+/// Rebuilds the table index whenever the folder finishes loading or a file is added
+fn index_loot_tables(
+    mut folder_events: EventReader<AssetEvent<LoadedFolder>>,
+    mut tables: ResMut<LootTables>,
+    folders: Res<Assets<LoadedFolder>>,
+) {
+    for event in folder_events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event
+        else {
+            continue;
+        };
+        if *id != tables.folder.id() {
+            continue;
+        }
+        let Some(folder) = folders.get(*id) else {
+            continue;
+        };
+        tables.tables = index_folder(folder, "loot.ron");
+        info!("Loaded {} loot tables", tables.tables.len());
+    }
+}
+
+fn drop_loot(
+    mut commands: Commands,
+    mut died_events: EventReader<UnitDiedEvent>,
+    loot_query: Query<(&Loot, &Transform)>,
+    tables: Res<LootTables>,
+    table_assets: Res<Assets<LootTable>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let mut rng = rand::thread_rng();
+    for event in died_events.read() {
+        let Ok((loot, transform)) = loot_query.get(event.entity) else {
+            continue;
+        };
+        let Some(table) = tables
+            .tables
+            .get(&loot.0)
+            .and_then(|handle| table_assets.get(handle))
+        else {
+            warn!("Loot table '{}' is not loaded", loot.0);
+            continue;
+        };
+
+        let drops = table.roll(&mut rng);
+        debug!("{:?} dropped {:?}", event.entity, drops);
+        for item in drops {
+            // Scatter the drops so they don't stack on top of each other
+            let scatter = Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU))
+                * rng.gen_range(0.0..PICKUP_SCATTER);
+            let position = transform.translation.xy() + scatter;
+            commands.spawn((
+                Name::new("Pickup"),
+                Mesh2d(meshes.add(Circle::new(PICKUP_RADIUS))),
+                MeshMaterial2d(materials.add(item.color())),
+                Transform::from_translation(position.extend(PICKUP_Z)),
+                Collider::ball(PICKUP_RADIUS),
+                Sensor,
+                ActiveEvents::COLLISION_EVENTS,
+                Pickup { item },
+                LevelEntity,
+            ));
+        }
+    }
+}
+
+/// Pulls pickups toward the player once it comes within the magnet radius
+fn attract_pickups(
+    mut pickup_query: Query<&mut Transform, (With<Pickup>, Without<Player>)>,
+    player_query: Query<&Transform, With<Player>>,
+    time: Res<Time>,
+) {
+    let Ok(player_transform) = player_query.single() else {
+        return;
+    };
+    let target = player_transform.translation.xy();
+    for mut transform in pickup_query.iter_mut() {
+        let offset = target - transform.translation.xy();
+        let distance = offset.length();
+        if distance > PICKUP_MAGNET_RADIUS || distance <= f32::EPSILON {
+            continue;
+        }
+        // Faster the closer it gets
+        let speed = PICKUP_MAGNET_SPEED * (1.0 - distance / PICKUP_MAGNET_RADIUS).max(0.2);
+        let step = (speed * time.delta_secs()).min(distance);
+        transform.translation += (offset / distance * step).extend(0.0);
+    }
+}
+
+fn collect_pickups(
+    mut commands: Commands,
+    mut collision_events: EventReader<CollisionEvent>,
+    pickup_query: Query<(&Pickup, &Transform)>,
+    mut player_query: Query<(&mut Unit, &mut Stats), With<Player>>,
+    mut purse: ResMut<Purse>,
+    mut hp_events: EventWriter<HpChangeEvent>,
+) {
+    // The despawn is deferred, so a second event in the same frame would collect it again
+    let mut collected = HashSet::new();
+    for event in collision_events.read() {
+        let CollisionEvent::Started(entity1, entity2, _) = event else {
+            continue;
+        };
+        let (pickup_entity, player) = if pickup_query.contains(*entity1) {
+            (*entity1, *entity2)
+        } else {
+            (*entity2, *entity1)
+        };
+        let (Ok((pickup, transform)), Ok((mut unit, mut stats))) = (
+            pickup_query.get(pickup_entity),
+            player_query.get_mut(player),
+        ) else {
+            continue;
+        };
+        if !collected.insert(pickup_entity) {
+            continue;
+        }
+
+        match &pickup.item {
+            Item::Gold(gold) => purse.gold += gold,
+            Item::HealthPotion(hp) => unit.heal(*hp, player, player, &mut hp_events),
+            Item::Tonic {
+                stat,
+                percent,
+                seconds,
+            } => {
+                let source = ModifierSource::Buff(format!("{:?} tonic", stat));
+                // Drinking another one refreshes the duration instead of stacking
+                stats.remove_source(&source);
+                stats.add(
+                    StatModifier::percent_add(*stat, *percent, source).with_duration(*seconds),
+                );
+            }
+        }
+        info!("Picked up {:?}", pickup.item);
+        spawn_floating_text(
+            &mut commands,
+            FloatingTextConfig {
+                text: pickup.item.label(),
+                color: pickup.item.color(),
+                position: transform.translation,
+                lifetime: Duration::from_millis(1200),
+                font_size: 20.0,
+                float_distance: 80.0,
+            },
+        );
+        commands.entity(pickup_entity).despawn();
+    }
+}
+
+fn setup_gold_ui(mut commands: Commands) {
+    commands.spawn((
+        Text::new("Gold 0"),
+        TextFont {
+            font_size: 20.0,
+            ..default()
+        },
+        TextColor(Color::srgb(1.0, 0.84, 0.0)),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(76.0),
+            left: Val::Px(20.0),
+            ..default()
+        },
+        GoldText,
+    ));
+}
+
+fn update_gold_ui(purse: Res<Purse>, mut gold_text: Query<&mut Text, With<GoldText>>) {
+    if !purse.is_changed() {
+        return;
+    }
+    if let Ok(mut text) = gold_text.single_mut() {
+        text.0 = format!("Gold {}", purse.gold);
+    }
+}
+
+#[derive(Default)]
+pub struct LootTableLoader;
+
+impl AssetLoader for LootTableLoader {
+    type Asset = LootTable;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        ron::de::from_bytes(&bytes).map_err(|e| {
+            std::io::Error::other(format!(
+                "Could not parse loot table {}: {}",
+                load_context.path().display(),
+                e
+            ))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["loot.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn rolls_guaranteed_gold_and_weighted_entries() {
+        let table: LootTable = ron::from_str(
+            r#"(
+                gold: Some((5, 5)),
+                guaranteed: [HealthPotion(50.0)],
+                rolls: 20,
+                entries: [
+                    (item: None, weight: 1),
+                    (item: Some(Gold(1)), weight: 1),
+                    (item: Some(HealthPotion(10.0)), weight: 0),
+                ],
+            )"#,
+        )
+        .unwrap();
+        let drops = table.roll(&mut StdRng::seed_from_u64(7));
+        assert_eq!(drops[0], Item::HealthPotion(50.0));
+        assert_eq!(drops[1], Item::Gold(5));
+        // Zero weight entries never drop
+        assert!(!drops.contains(&Item::HealthPotion(10.0)));
+        assert!(drops[2..].iter().all(|item| *item == Item::Gold(1)));
+    }
+}
@@ -8,25 +8,45 @@
 //! | `S`                  | Move down     |
 //! | `A`                  | Move left     |
 //! | `D`                  | Move right    |
+//! | `1`                  | Companions attack my target |
+//! | `2`                  | Companions hold position    |
+//! | `3`                  | Companions follow           |
 
 use crate::ai::AIPlugin;
 use crate::berserker::Berserker;
 use crate::berserker::BerserkerPlugin;
+use crate::boss::{BossPlugin, CameraFocus};
 use crate::collider::*;
+use crate::companion::{Companion, CompanionPlugin};
 use crate::constants::*;
+use crate::defense::DefensePlugin;
+use crate::experience::{Experience, ExperiencePlugin};
 use crate::float_text::FloatingTextPlugin;
-use crate::force::Force;
+use crate::force::FactionPlugin;
+use crate::game_state::{GameStatePlugin, GameplaySet};
 use crate::global_entity_map::*;
-use crate::level::level::LevelPlugin;
+use crate::level::level::{CurrentLevel, LevelEntity, LevelPlugin};
+use crate::loot::LootPlugin;
 use crate::move_components::MoveComponentsPlugin;
 use crate::movement::SprintCD;
 use crate::movement::SprintReadyLogged;
 use crate::movement::SprintReadyPlugin;
 use crate::particle::ParticlePlugin;
+use crate::patrol::PatrolPlugin;
+use crate::respawn::RespawnPlugin;
 use crate::rotation::RotationPlugin;
+use crate::save::{SavePlugin, SaveState, UniqueEnemy};
+use crate::spawn_properties::SpawnProperties;
+use crate::squad::SquadPlugin;
+use crate::stats::StatsPlugin;
 use crate::unit::Unit;
 use crate::unit_death::UnitDeathPlugin;
-use crate::level::tiled::{ObjectLayers, TiledMapPlugin};
+use crate::unit_template::{SpawnUnitExt, UnitTemplatePlugin};
+use crate::wave::WavePlugin;
+use crate::level::tiled::{LevelData, ObjectLayers, TiledMapPlugin};
+use crate::level::zone::ZonePlugin;
+use crate::level::chunks::ChunkStreamingPlugin;
+use crate::level::layers::Parallax;
 use bevy::log::LogPlugin;
 use bevy::{core_pipeline::bloom::Bloom, prelude::*};
 use bevy_inspector_egui::bevy_egui::EguiPlugin;
@@ -44,29 +64,44 @@ pub mod custom_move;
 mod ai;
 mod animation_base;
 mod berserker;
+mod boss;
 mod collider;
 mod collisions;
+mod companion;
 mod constants;
 mod damage;
+mod defense;
+mod difficulty;
 mod enemy;
+mod experience;
 mod float_text;
 mod force;
+mod game_state;
 mod global_entity_map;
 mod health_bar;
 mod input;
 mod input_move_map;
 mod iterpolation;
 mod lerp_animation;
+mod loot;
 mod move_components;
 mod move_database;
 mod movement;
 mod particle;
+mod patrol;
 mod physics;
+mod respawn;
 mod rotation;
+mod save;
+mod spawn_properties;
+mod squad;
+mod stats;
 mod stun;
 mod sword_trail;
 mod unit;
 mod unit_death;
+mod unit_template;
+mod wave;
 mod weapon;
 mod level;
 
@@ -82,13 +117,20 @@ fn main() {
         .add_plugins(crate::custom_move::MovePlugin)
         .add_plugins(crate::health_bar::HealthBarPlugin)
         .add_plugins(crate::unit::UnitPlugin)
+        .add_plugins(UnitTemplatePlugin)
         .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
         .add_plugins(RapierDebugRenderPlugin::default())
         .add_plugins(crate::sword_trail::SwordTrailPlugin)
         .add_plugins(GlobalEntityMapPlugin)
         .add_plugins(MoveComponentsPlugin)
         .add_plugins(crate::animation_base::AnimationDatabasePlugin)
+        .add_plugins(FactionPlugin)
         .add_plugins(AIPlugin)
+        .add_plugins(PatrolPlugin)
+        .add_plugins(SquadPlugin)
+        .add_plugins(DefensePlugin)
+        .add_plugins(BossPlugin)
+        .add_plugins(CompanionPlugin)
         .add_plugins(RotationPlugin)
         .add_plugins(ParticlePlugin)
         .add_plugins(TweeningPlugin)
@@ -99,19 +141,30 @@ fn main() {
         .add_plugins(crate::stun::StunPlugin)
         .add_plugins(TransformInterpolationPlugin::default())
         .add_plugins(TiledMapPlugin)
+        .add_plugins(ZonePlugin)
+        .add_plugins(ChunkStreamingPlugin)
+        .add_plugins(GameStatePlugin)
         .add_plugins(LevelPlugin)
+        .add_plugins(WavePlugin)
+        .add_plugins(RespawnPlugin)
+        .add_plugins(SavePlugin)
+        .add_plugins(ExperiencePlugin)
+        .add_plugins(StatsPlugin)
+        .add_plugins(LootPlugin)
         .add_systems(Startup, (setup_scene, setup_instructions, setup_camera, register_object_layer_systems))
         .add_systems(
             Update,
             (
                 crate::input::handle_input,
                 crate::input_move_map::input_map_to_move,
+                crate::movement::update_attack_slow,
                 crate::movement::move_player,
                 crate::collisions::handle_collisions,
                 crate::physics::update_knockback_timers,
                 update_camera,
             )
-                .chain(),
+                .chain()
+                .in_set(GameplaySet),
         )
         .run();
 }
@@ -143,131 +196,57 @@ fn register_object_layer_systems(
 
 fn spawn_entities_from_objects(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut global_map: ResMut<GlobalEntityMap>,
     object_layers: Res<ObjectLayers>,
+    level_data: Res<LevelData>,
+    current_level: Res<CurrentLevel>,
+    save_state: Res<SaveState>,
+    player_query: Query<(), With<Player>>,
 ) {
     info!("Spawning entities from SpawnPoint layer");
     if let Some(spawn_objects) = object_layers.layer_data.get("SpawnPoint") {
         for object in spawn_objects {
+            if !object_layers.is_new_object("SpawnPoint", object.id()) {
+                // Hot reload: the unit is alive or already dead, don't spawn it again
+                debug!("Keeping existing unit for '{}' (id {})", object.name, object.id());
+                continue;
+            }
             info!("Found object: name='{}', x={}, y={}", object.name, object.x, object.y);
+            let mut spawn = SpawnProperties::from_object(object);
+            let template = spawn.template_or(&object.name).to_lowercase();
+            let position = level_data.geometry.object_to_world(Vec2::new(object.x, object.y));
+            if template == "hero" && !player_query.is_empty() {
+                // The player carries over from the previous level
+                continue;
+            }
+            if let Some(save) = save_state.restore.as_ref().filter(|_| template == "hero") {
+                spawn.weapon = Some(save.player.weapon);
+            }
+            let unique = (spawn.unique || template == "boss")
+                .then(|| UniqueEnemy::new(&current_level.map, object.id()));
+            if unique
+                .as_ref()
+                .is_some_and(|unique| save_state.killed_uniques.contains(&unique.0))
+            {
+                info!("Skipping '{}', already killed", object.name);
+                continue;
+            }
 
-            match object.name.as_str() {
-                "Hero" => {
-                    info!("Spawning Hero at ({}, {})", object.x, -object.y);
-                    let player = commands
-                        .spawn((
-                            Player,
-                            Berserker{level: 0},
-                            Mesh2d(meshes.add(Circle::new(MESH_RADIUS))),
-                            MeshMaterial2d(materials.add(PLAYER_COLOR)),
-                            Transform::from_xyz(object.x, -object.y, 2.0), // Note: flip Y for Tiled coordinate system
-                            DynamicPhysicsBundle::new_ball(MESH_RADIUS),
-                            Velocity::zero(),
-                            SprintCD(0.0),
-                            SprintReadyLogged(false),
-                            Unit::builder()
-                                .name("Hero")
-                                .max_hp(1000.0)
-                                .unitType(unit::UnitType::Hero)
-                                .build(),
-                            Force {
-                                force: FORCE_PLAYER,
-                            },
-                            crate::ai::TargetDetector {
-                                target: Entity::PLACEHOLDER,
-                                alert_range: ALERT_RANGE,
-                                dis_alert_range: DIS_ALERT_RANGE,
-                                lock_type: ai::LockType::Free,
-                            },
-                        ))
-                        .with_children(|parent| {
-                            // Left eye (larger)
-                            parent.spawn((
-                                Mesh2d(meshes.add(Circle::new(MESH_RADIUS * 0.15))),
-                                MeshMaterial2d(materials.add(Color::BLACK)),
-                                Transform::from_xyz(-MESH_RADIUS * 0.3, MESH_RADIUS * 0.2, 0.1),
-                            ));
-
-                            // Right eye (smaller)
-                            parent.spawn((
-                                Mesh2d(meshes.add(Circle::new(MESH_RADIUS * 0.1))),
-                                MeshMaterial2d(materials.add(Color::BLACK)),
-                                Transform::from_xyz(MESH_RADIUS * 0.4, MESH_RADIUS * 0.25, 0.1),
-                            ));
-                        })
-                        .id();
-
-                    crate::weapon::equip_sword(
-                        &mut commands,
-                        &mut meshes,
-                        &mut materials,
-                        player,
-                        Vec3::new(50.0, 40.0, 0.1),
-                        0.5,
-                        &mut global_map,
-                    );
-                },
-                "Enemy" => {
-                    info!("Spawning Enemy at ({}, {})", object.x, -object.y);
-                    let enemy = commands
-                        .spawn((
-                            Mesh2d(meshes.add(Rectangle::new(MESH_RADIUS * 2., MESH_RADIUS * 2.))),
-                            MeshMaterial2d(materials.add(ENEMY_COLOR)),
-                            Transform::from_xyz(object.x, -object.y, 1.0), // Note: flip Y for Tiled coordinate system
-                            DynamicPhysicsBundle::new_box(MESH_RADIUS, MESH_RADIUS),
-                            Velocity::zero(),
-                            Unit::builder()
-                                .name("Guard")
-                                .max_hp(500.0)
-                                .unitType(unit::UnitType::SwordMan)
-                                .build(),
-                            crate::ai::TargetDetector {
-                                target: Entity::PLACEHOLDER,
-                                alert_range: ALERT_RANGE,
-                                dis_alert_range: DIS_ALERT_RANGE,
-                                lock_type: ai::LockType::Lock,
-                            },
-                            Force { force: FORCE_ENEMY },
-                            crate::ai::AI::new(
-                                global_map
-                                    .unittype_aioptions
-                                    .get(&unit::UnitType::SwordMan)
-                                    .cloned()
-                                    .unwrap_or_default(),
-                            ),
-                        ))
-                        .with_children(|parent| {
-                            // Left eye (smaller for enemy)
-                            parent.spawn((
-                                Mesh2d(meshes.add(Circle::new(MESH_RADIUS * 0.12))),
-                                MeshMaterial2d(materials.add(Color::BLACK)),
-                                Transform::from_xyz(-MESH_RADIUS * 0.4, MESH_RADIUS * 0.3, 0.1),
-                            ));
-
-                            // Right eye (larger for enemy)
-                            parent.spawn((
-                                Mesh2d(meshes.add(Circle::new(MESH_RADIUS * 0.18))),
-                                MeshMaterial2d(materials.add(Color::BLACK)),
-                                Transform::from_xyz(MESH_RADIUS * 0.35, MESH_RADIUS * 0.4, 0.1),
-                            ));
-                        })
-                        .id();
-
-                    crate::weapon::equip_sword(
-                        &mut commands,
-                        &mut meshes,
-                        &mut materials,
-                        enemy,
-                        Vec3::new(50.0, 40.0, 0.1),
-                        0.5,
-                        &mut global_map,
-                    );
-                },
-                _ => {
-                    info!("Unknown object type: {}", object.name);
+            let mut unit = commands.spawn_unit_with(template.clone(), position, spawn);
+            if template != "hero" {
+                unit.insert(LevelEntity);
+            }
+            if let Some(unique) = unique {
+                unit.insert(unique);
+            }
+            // Roles that templates can't express
+            match template.as_str() {
+                "hero" => {
+                    unit.insert(hero_bundle());
+                }
+                "companion" => {
+                    unit.insert(Companion::new(position));
                 }
+                _ => {}
             }
         }
     } else {
@@ -275,6 +254,17 @@ fn spawn_entities_from_objects(
     }
 }
 
+/// Components that make a hero unit the player, shared by the first spawn and respawns
+pub fn hero_bundle() -> impl Bundle {
+    (
+        Player,
+        Berserker { level: 0 },
+        SprintCD(0.0),
+        SprintReadyLogged(false),
+        Experience::default(),
+    )
+}
+
 fn setup_instructions(mut commands: Commands) {
     commands.spawn((
         Text::new("Move the light with WASD.\nThe camera will smoothly track the light."),
@@ -291,13 +281,23 @@ fn setup_camera(mut commands: Commands) {
     commands.spawn((Camera2d, Bloom::NATURAL));
 }
 
-/// Update the camera position by tracking the player.
+/// Update the camera position by tracking the player, or the focus target while one is set,
+/// then move parallax layers relative to it.
 fn update_camera(
     mut camera: Query<&mut Transform, (With<Camera2d>, Without<Player>)>,
     player: Query<&Transform, (With<Player>, Without<Camera2d>)>,
+    focus_query: Query<&Transform, (Without<Camera2d>, Without<Parallax>)>,
+    mut parallax_query: Query<(&mut Transform, &Parallax), (Without<Camera2d>, Without<Player>)>,
+    camera_focus: Res<CameraFocus>,
     time: Res<Time>,
 ) {
-    if let (Ok(mut camera_transform), Ok(player_transform)) = (camera.get_single_mut(), player.get_single()) {
+    let focus_transform = camera_focus
+        .target
+        .and_then(|target| focus_query.get(target).ok());
+    if let (Ok(mut camera_transform), Some(player_transform)) = (
+        camera.single_mut(),
+        focus_transform.or(player.single().ok()),
+    ) {
         let Vec3 { x, y, .. } = player_transform.translation;
         let direction = Vec3::new(x, y, camera_transform.translation.z);
 
@@ -307,4 +307,14 @@ fn update_camera(
             .translation
             .smooth_nudge(&direction, CAMERA_DECAY_RATE, time.delta_secs());
     }
+
+    let Ok(camera_transform) = camera.single() else {
+        return;
+    };
+    let camera_position = camera_transform.translation.xy();
+    for (mut layer_transform, parallax) in parallax_query.iter_mut() {
+        let position = parallax.position(camera_position);
+        layer_transform.translation.x = position.x;
+        layer_transform.translation.y = position.y;
+    }
 }
@@ -1,4 +1,5 @@
 use crate::custom_move::*;
+use crate::game_state::GameplaySet;
 use crate::global_entity_map::GlobalEntityMap;
 use crate::sword_trail::SwordTrail;
 use bevy::prelude::*;
@@ -10,7 +11,7 @@ impl Plugin for MoveComponentsPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<MoveActiveEvent>()
             .add_event::<MoveRecoveryEvent>()
-            .add_systems(Update, (handle_start_move, handle_end_move));
+            .add_systems(Update, (handle_start_move, handle_end_move).in_set(GameplaySet));
     }
 }
 
@@ -1,9 +1,11 @@
 use crate::{
-    ai::{LockType, TargetDetector}, berserker::Berserker, constants::*, float_text::spawn_sprint_ready_text, physics::apply_impulse
+    ai::{LockType, TargetDetector}, constants::*, float_text::spawn_sprint_ready_text, physics::apply_impulse
 };
 use bevy::{ecs::component, prelude::*};
 use bevy_rapier2d::prelude::Velocity;
-use crate::custom_move::Move;
+use crate::custom_move::{Move, PlayerMove};
+use crate::game_state::GameplaySet;
+use crate::stats::{ModifierSource, StatKind, StatModifier, Stats};
 
 #[derive(Component)]
 pub struct SprintCD(pub f64);
@@ -12,6 +14,25 @@ pub struct SprintCD(pub f64);
 pub struct SprintReadyLogged(pub bool);
 
 
+/// Slows the player down while a move is being performed
+pub fn update_attack_slow(mut player_query: Query<(&mut Stats, Has<PlayerMove>), With<crate::Player>>) {
+    for (mut stats, is_attacking) in player_query.iter_mut() {
+        let source = ModifierSource::Attacking;
+        if is_attacking == stats.has_source(&source) {
+            continue;
+        }
+        if is_attacking {
+            stats.add(StatModifier::percent_multiply(
+                StatKind::Speed,
+                ATTACK_SPEED_FACTOR - 1.0,
+                source,
+            ));
+        } else {
+            stats.remove_source(&source);
+        }
+    }
+}
+
 pub fn move_player(
     mut player: Single<
         (Entity, &mut Transform, &TargetDetector, &mut Velocity, &mut SprintCD),
@@ -20,7 +41,7 @@ pub fn move_player(
     time: Res<Time>,
     mut move_events: EventReader<crate::input::MoveEvent>,
     move_query: Query<&crate::custom_move::PlayerMove, With<crate::Player>>,
-    berserker_query: Query<&Berserker, With<crate::Player>>,
+    stats_query: Query<&Stats, With<crate::Player>>,
     global_entity_map: Res<crate::global_entity_map::GlobalEntityMap>,
     weapon_move_query: Query<&Move>,
 ) {
@@ -113,20 +134,11 @@ pub fn move_player(
             PLAYER_SPEED
         };
         
-        // Check for Berserker component and modify speed if level 1
-        let mut current_speed = if let Ok(berserker) = berserker_query.get(player.0) {
-            if berserker.level == 1 {
-                base_speed + BERSERKER_MOVE_SPEED
-            } else {
-                base_speed
-            }
-        } else {
-            base_speed
+        // Berserker stance, attack slow, levels and buffs
+        let current_speed = match stats_query.get(player.0) {
+            Ok(stats) => stats.apply(StatKind::Speed, base_speed),
+            Err(_) => base_speed,
         };
-
-        if is_attacking {
-            current_speed = current_speed * ATTACK_SPEED_FACTOR;
-        }
         
         trace!(
             "Walking: direction={:?}, is_attacking={}, base_speed={}, current_speed={}",
@@ -188,7 +200,7 @@ pub struct SprintReadyPlugin;
 impl Plugin for SprintReadyPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(SprintCheckTimer::default())
-            .add_systems(Update, check_sprint_ready);
+            .add_systems(Update, check_sprint_ready.in_set(GameplaySet));
     }
 }
 
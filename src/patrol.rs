@@ -0,0 +1,354 @@
+use crate::ai::{AI, AISuspended, TargetDetector};
+use crate::constants::*;
+use crate::level::tiled::{LevelData, ObjectLayers};
+use crate::stun::Stun;
+use crate::unit::Unit;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::Velocity;
+use rand::Rng;
+use std::collections::HashMap;
+use tiled::{ObjectShape, PropertyValue};
+
+/// A patrol route authored as a polyline (walked back and forth) or a polygon (looped)
+#[derive(Clone, Debug)]
+pub struct PatrolPath {
+    pub points: Vec<Vec2>,
+    pub looped: bool,
+}
+
+/// All patrol routes from the Patrol object layer, keyed by object name
+#[derive(Resource, Default)]
+pub struct PatrolPaths {
+    pub paths: HashMap<String, PatrolPath>,
+}
+
+/// What an AI does while it has no target
+#[derive(Component, Clone, Debug, PartialEq)]
+pub enum IdleBehavior {
+    /// Stand at the spawn position
+    Guard,
+    /// Walk to random points around the spawn position
+    Wander { radius: f32 },
+    /// Follow a named route from `PatrolPaths`
+    Patrol { path: String },
+}
+
+/// Where the unit was spawned, used as its guard post
+#[derive(Component)]
+pub struct SpawnAnchor {
+    pub position: Vec2,
+}
+
+#[derive(Component)]
+pub struct IdleState {
+    pub waypoint_index: usize,
+    pub waypoint_step: i32,
+    pub wander_target: Option<Vec2>,
+    pub wait_timer: Timer,
+    pub returning: bool,
+    pub engaged: bool,
+    /// Route of a patrolling unit, resolved from `PatrolPaths` when either of them changes
+    pub route: Option<PatrolPath>,
+}
+
+impl Default for IdleState {
+    fn default() -> Self {
+        Self {
+            waypoint_index: 0,
+            waypoint_step: 1,
+            wander_target: None,
+            wait_timer: Timer::from_seconds(IDLE_WAIT_TIME, TimerMode::Once),
+            returning: false,
+            engaged: false,
+            route: None,
+        }
+    }
+}
+
+impl IdleBehavior {
+    /// Read the idle behavior from a spawn object's custom properties.
+    ///
+    /// `idle` selects "guard", "wander" or "patrol"; `patrol_path` names the route and
+    /// `wander_radius` sets the wander distance. A `patrol_path` alone implies patrol.
+    pub fn from_properties(properties: &tiled::Properties) -> Self {
+        let patrol_path = match properties.get("patrol_path") {
+            Some(PropertyValue::StringValue(path)) if !path.is_empty() => Some(path.clone()),
+            _ => None,
+        };
+        let wander_radius = match properties.get("wander_radius") {
+            Some(PropertyValue::FloatValue(radius)) => *radius,
+            Some(PropertyValue::IntValue(radius)) => *radius as f32,
+            _ => DEFAULT_WANDER_RADIUS,
+        };
+
+        match properties.get("idle") {
+            Some(PropertyValue::StringValue(idle)) => match idle.as_str() {
+                "guard" => IdleBehavior::Guard,
+                "wander" => IdleBehavior::Wander {
+                    radius: wander_radius,
+                },
+                "patrol" => match patrol_path {
+                    Some(path) => IdleBehavior::Patrol { path },
+                    None => {
+                        warn!(
+                            "Idle behavior 'patrol' without a patrol_path, falling back to guard"
+                        );
+                        IdleBehavior::Guard
+                    }
+                },
+                other => {
+                    warn!("Unknown idle behavior '{}', falling back to guard", other);
+                    IdleBehavior::Guard
+                }
+            },
+            _ => match patrol_path {
+                Some(path) => IdleBehavior::Patrol { path },
+                None => IdleBehavior::Guard,
+            },
+        }
+    }
+}
+
+pub struct PatrolPlugin;
+
+impl Plugin for PatrolPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PatrolPaths>()
+            .add_systems(Startup, register_patrol_layer_system);
+    }
+}
+
+fn register_patrol_layer_system(mut commands: Commands, mut object_layers: ResMut<ObjectLayers>) {
+    let load_patrol_paths_system = commands.register_system(load_patrol_paths);
+    object_layers
+        .loader_systems
+        .insert(PATROL_LAYER.to_string(), load_patrol_paths_system);
+    info!("Registered system for layer: {}", PATROL_LAYER);
+}
+
+fn load_patrol_paths(
+    object_layers: Res<ObjectLayers>,
+    level_data: Res<LevelData>,
+    mut patrol_paths: ResMut<PatrolPaths>,
+) {
+    let Some(objects) = object_layers.layer_data.get(PATROL_LAYER) else {
+        info!("No {} layer found in object layers", PATROL_LAYER);
+        return;
+    };
+
+    patrol_paths.paths.clear();
+    for object in objects {
+        let (points, looped) = match &object.shape {
+            ObjectShape::Polyline { points } => (points, false),
+            ObjectShape::Polygon { points } => (points, true),
+            _ => {
+                warn!(
+                    "Patrol object '{}' (id {}) is not a polyline or polygon, skipping",
+                    object.name,
+                    object.id()
+                );
+                continue;
+            }
+        };
+
+        // Polyline points are relative to the object origin
+        let points: Vec<Vec2> = points
+            .iter()
+            .map(|(px, py)| {
+                level_data
+                    .geometry
+                    .object_to_world(Vec2::new(object.x + px, object.y + py))
+            })
+            .collect();
+
+        if points.is_empty() {
+            warn!("Patrol path '{}' has no points, skipping", object.name);
+            continue;
+        }
+
+        info!(
+            "Loaded patrol path '{}' with {} points (looped: {})",
+            object.name,
+            points.len(),
+            looped
+        );
+        patrol_paths
+            .paths
+            .insert(object.name.clone(), PatrolPath { points, looped });
+    }
+}
+
+/// Looks up the route of newly spawned patrolling units, and of all of them when the Patrol
+/// layer is (re)loaded, so a missing route is reported once instead of every frame
+pub fn resolve_patrol_routes(
+    mut unit_query: Query<(Entity, Ref<IdleBehavior>, &mut IdleState)>,
+    patrol_paths: Res<PatrolPaths>,
+) {
+    for (entity, behavior, mut state) in unit_query.iter_mut() {
+        if !behavior.is_added() && !patrol_paths.is_changed() {
+            continue;
+        }
+        let IdleBehavior::Patrol { path } = &*behavior else {
+            continue;
+        };
+        state.route = patrol_paths.paths.get(path).cloned();
+        if state.route.is_none() {
+            warn!(
+                "Patrol path '{}' not found for entity {:?}, guarding spawn",
+                path, entity
+            );
+        }
+    }
+}
+
+/// Moves AI units that have no target according to their idle behavior
+pub fn ai_idle_system(
+    mut ai_query: Query<
+        (
+            Entity,
+            &TargetDetector,
+            &mut Transform,
+            &mut Velocity,
+            &Unit,
+            &IdleBehavior,
+            &SpawnAnchor,
+            &mut IdleState,
+        ),
+        (With<AI>, Without<AISuspended>),
+    >,
+    stun_query: Query<&Stun>,
+    time: Res<Time>,
+) {
+    let mut rng = rand::thread_rng();
+
+    for (entity, detector, mut transform, mut velocity, unit, behavior, anchor, mut state) in
+        ai_query.iter_mut()
+    {
+        if detector.target != Entity::PLACEHOLDER {
+            state.engaged = true;
+            continue;
+        }
+
+        // Target was just lost - walk back before resuming the idle behavior
+        if state.engaged {
+            debug!("AI entity {:?} disengaged, returning to post", entity);
+            state.engaged = false;
+            state.returning = true;
+            state.wander_target = None;
+        }
+
+        if stun_query.get(entity).is_ok() {
+            continue;
+        }
+
+        let position = transform.translation.xy();
+        let speed = unit.speed * IDLE_SPEED_FACTOR;
+
+        let destination = match behavior {
+            IdleBehavior::Guard => {
+                if position.distance(anchor.position) <= WAYPOINT_REACHED_RANGE {
+                    state.returning = false;
+                    None
+                } else {
+                    Some(anchor.position)
+                }
+            }
+            IdleBehavior::Wander { radius } => {
+                if state.returning {
+                    if position.distance(anchor.position) <= WAYPOINT_REACHED_RANGE {
+                        state.returning = false;
+                        state.wait_timer.reset();
+                        None
+                    } else {
+                        Some(anchor.position)
+                    }
+                } else if let Some(target) = state.wander_target {
+                    if position.distance(target) <= WAYPOINT_REACHED_RANGE {
+                        state.wander_target = None;
+                        state.wait_timer.reset();
+                        None
+                    } else {
+                        Some(target)
+                    }
+                } else {
+                    state.wait_timer.tick(time.delta());
+                    if state.wait_timer.finished() {
+                        let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+                        let distance = rng.gen_range(0.0..*radius);
+                        let target = anchor.position + Vec2::from_angle(angle) * distance;
+                        state.wander_target = Some(target);
+                        Some(target)
+                    } else {
+                        None
+                    }
+                }
+            }
+            IdleBehavior::Patrol { .. } => match state.route.take() {
+                Some(patrol_path) => {
+                    if state.returning {
+                        // Resume from the closest waypoint instead of the one we left
+                        state.waypoint_index = closest_waypoint(&patrol_path.points, position);
+                        state.returning = false;
+                    }
+                    state.waypoint_index %= patrol_path.points.len();
+                    let waypoint = patrol_path.points[state.waypoint_index];
+                    if position.distance(waypoint) <= WAYPOINT_REACHED_RANGE {
+                        advance_waypoint(&mut state, &patrol_path);
+                    }
+                    let destination = patrol_path.points[state.waypoint_index];
+                    state.route = Some(patrol_path);
+                    Some(destination)
+                }
+                // Already reported by resolve_patrol_routes
+                None => {
+                    Some(anchor.position).filter(|p| position.distance(*p) > WAYPOINT_REACHED_RANGE)
+                }
+            },
+        };
+
+        if let Some(destination) = destination {
+            let direction = (destination - position).normalize_or_zero();
+            // Don't overshoot the destination in the last frame
+            let speed =
+                speed.min(position.distance(destination) / time.delta_secs().max(f32::EPSILON));
+            velocity.linvel = direction * speed;
+
+            if direction != Vec2::ZERO {
+                let angle = direction.y.atan2(direction.x) - std::f32::consts::FRAC_PI_2;
+                transform.rotation = Quat::from_rotation_z(angle);
+            }
+        }
+    }
+}
+
+fn closest_waypoint(points: &[Vec2], position: Vec2) -> usize {
+    points
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| {
+            a.distance_squared(position)
+                .total_cmp(&b.distance_squared(position))
+        })
+        .map(|(index, _)| index)
+        .unwrap_or(0)
+}
+
+fn advance_waypoint(state: &mut IdleState, patrol_path: &PatrolPath) {
+    let count = patrol_path.points.len();
+    if count <= 1 {
+        state.waypoint_index = 0;
+        return;
+    }
+
+    if patrol_path.looped {
+        state.waypoint_index = (state.waypoint_index + 1) % count;
+        return;
+    }
+
+    // Walk polylines back and forth
+    let next = state.waypoint_index as i32 + state.waypoint_step;
+    if next < 0 || next >= count as i32 {
+        state.waypoint_step = -state.waypoint_step;
+    }
+    state.waypoint_index = (state.waypoint_index as i32 + state.waypoint_step) as usize;
+}
@@ -0,0 +1,445 @@
+use crate::Player;
+use crate::constants::*;
+use crate::experience::Experience;
+use crate::game_state::{GameState, GameplaySet};
+use crate::global_entity_map::GlobalEntityMap;
+use crate::hero_bundle;
+use crate::level::level::{CurrentLevel, LevelEntity, LevelTransitionEvent};
+use crate::level::tiled::{LevelData, ObjectLayers, TiledMapLoadState};
+use crate::level::zone::{EnterZoneEvent, Zone};
+use crate::unit::{HpChangeEvent, HpChangeType, Invulnerable, Unit};
+use crate::unit_template::SpawnUnitExt;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use std::collections::HashMap;
+
+/// What happens to the other units of the level when the player respawns
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EnemyReset {
+    /// Leave everything as it was
+    Keep,
+    /// Heal surviving units back to full
+    #[default]
+    Heal,
+    /// Rebuild the level, bringing back everything the player killed
+    Respawn,
+}
+
+/// Rules for dying and coming back
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct RespawnSettings {
+    /// Respawns before the game is over
+    pub lives: u32,
+    /// Times the player can continue from game over with full lives
+    pub continues: u32,
+    /// Fraction of max HP the player respawns with
+    pub hp_fraction: f32,
+    pub enemy_reset: EnemyReset,
+}
+
+impl Default for RespawnSettings {
+    fn default() -> Self {
+        Self {
+            lives: PLAYER_LIVES,
+            continues: PLAYER_CONTINUES,
+            hp_fraction: RESPAWN_HP_FRACTION,
+            enemy_reset: EnemyReset::default(),
+        }
+    }
+}
+
+/// Where the player comes back and how many tries are left
+#[derive(Resource, Debug, Default)]
+pub struct RespawnState {
+    pub lives: u32,
+    pub continues: u32,
+    /// Map the positions below belong to
+    map: String,
+    /// Where the player arrived on the map
+    level_start: Vec2,
+    /// Last checkpoint touched on the map
+    checkpoint: Option<String>,
+    /// Level of the hero that died, carried over to the next one
+    experience: Option<Experience>,
+}
+
+impl RespawnState {
+    /// Map the level start and checkpoint belong to, empty until the player arrives
+    pub fn map(&self) -> &str {
+        &self.map
+    }
+
+    pub fn checkpoint(&self) -> Option<&str> {
+        self.checkpoint.as_deref()
+    }
+
+    /// Makes a checkpoint of the current map the respawn point, e.g. when loading a save
+    pub fn set_checkpoint(&mut self, name: impl Into<String>) {
+        self.checkpoint = Some(name.into());
+    }
+}
+
+/// Checkpoint positions of the current map, by name
+#[derive(Resource, Default)]
+pub struct Checkpoints {
+    pub points: HashMap<String, Vec2>,
+}
+
+/// Hero that ran out of HP, playing its death effect before respawning or ending the game
+#[derive(Component)]
+pub struct PlayerDying {
+    timer: Timer,
+}
+
+/// Sent when the hero comes back after losing a life, for fights and effects to reset
+#[derive(Event, Debug, Clone)]
+pub struct PlayerRespawnedEvent {
+    pub entity: Entity,
+    pub position: Vec2,
+}
+
+/// Freshly respawned hero that still needs its HP penalty applied
+#[derive(Component)]
+struct Respawned {
+    hp_fraction: f32,
+}
+
+#[derive(Component)]
+struct LivesText;
+
+#[derive(Component)]
+struct ContinueText;
+
+pub struct RespawnPlugin;
+
+impl Plugin for RespawnPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RespawnSettings>()
+            .init_resource::<RespawnState>()
+            .init_resource::<Checkpoints>()
+            .add_event::<PlayerRespawnedEvent>()
+            .add_systems(Startup, (register_checkpoint_layer_system, setup_lives_ui))
+            .add_systems(OnEnter(GameState::Loading), reset_lives)
+            .add_systems(OnEnter(GameState::GameOver), spawn_continue_text)
+            .add_systems(OnExit(GameState::GameOver), despawn_continue_text)
+            .add_systems(
+                Update,
+                (
+                    (
+                        track_level_start,
+                        activate_checkpoints,
+                        start_player_death,
+                        play_player_death,
+                    )
+                        .chain()
+                        .in_set(GameplaySet),
+                    apply_respawn_penalty,
+                    continue_game.run_if(in_state(GameState::GameOver)),
+                    update_lives_ui,
+                ),
+            );
+    }
+}
+
+fn register_checkpoint_layer_system(mut commands: Commands, mut object_layers: ResMut<ObjectLayers>) {
+    let load_checkpoints_system = commands.register_system(load_checkpoints);
+    object_layers
+        .loader_systems
+        .insert(CHECKPOINT_LAYER.to_string(), load_checkpoints_system);
+    info!("Registered system for layer: {}", CHECKPOINT_LAYER);
+}
+
+/// Checkpoints are sensor zones; the player respawns at the centre of the last one touched
+fn load_checkpoints(
+    object_layers: Res<ObjectLayers>,
+    level_data: Res<LevelData>,
+    mut checkpoints: ResMut<Checkpoints>,
+) {
+    checkpoints.points.clear();
+    let Some(objects) = object_layers.layer_data.get(CHECKPOINT_LAYER) else {
+        return;
+    };
+    for object in objects {
+        let (width, height) = match object.shape {
+            tiled::ObjectShape::Rect { width, height } => (width, height),
+            _ => (0.0, 0.0),
+        };
+        let center = Vec2::new(object.x + width / 2.0, object.y + height / 2.0);
+        checkpoints
+            .points
+            .insert(object.name.clone(), level_data.geometry.object_to_world(center));
+    }
+    info!("Loaded {} checkpoints", checkpoints.points.len());
+}
+
+fn reset_lives(settings: Res<RespawnSettings>, mut respawn: ResMut<RespawnState>) {
+    // Loading only happens for a new game; continues keep playing the loaded level
+    *respawn = RespawnState {
+        lives: settings.lives,
+        continues: settings.continues,
+        ..Default::default()
+    };
+}
+
+/// Remembers where the player entered each map, the respawn point until a checkpoint is touched
+fn track_level_start(
+    mut respawn: ResMut<RespawnState>,
+    current_level: Res<CurrentLevel>,
+    map_query: Query<&TiledMapLoadState>,
+    player_query: Query<&Transform, With<Player>>,
+) {
+    if respawn.map == current_level.map || current_level.entry.is_some() {
+        return;
+    }
+    let map_loaded = map_query.iter().any(|load_state| load_state.load_flag);
+    let Ok(transform) = player_query.single() else {
+        return;
+    };
+    if map_loaded {
+        respawn.map = current_level.map.clone();
+        respawn.level_start = transform.translation.xy();
+        respawn.checkpoint = None;
+        debug!("Level start for {} at {:?}", respawn.map, respawn.level_start);
+    }
+}
+
+fn activate_checkpoints(
+    mut enter_events: EventReader<EnterZoneEvent>,
+    mut respawn: ResMut<RespawnState>,
+    zone_query: Query<&Zone>,
+    player_query: Query<(), With<Player>>,
+) {
+    for event in enter_events.read() {
+        if player_query.get(event.entity).is_err() {
+            continue;
+        }
+        let Ok(zone) = zone_query.get(event.zone) else {
+            continue;
+        };
+        if zone.layer != CHECKPOINT_LAYER || respawn.checkpoint.as_ref() == Some(&event.name) {
+            continue;
+        }
+        info!("Checkpoint '{}' activated", event.name);
+        respawn.checkpoint = Some(event.name.clone());
+    }
+}
+
+/// Takes the hero out of play instead of despawning it like other units
+fn start_player_death(
+    mut commands: Commands,
+    mut hp_events: EventReader<HpChangeEvent>,
+    mut respawn: ResMut<RespawnState>,
+    player_query: Query<Option<&Experience>, With<Player>>,
+) {
+    for event in hp_events.read() {
+        if event.new_hp > 0.0 {
+            continue;
+        }
+        let Ok(experience) = player_query.get(event.entity) else {
+            continue;
+        };
+        info!("Player {:?} died", event.entity);
+        respawn.experience = experience.copied();
+        commands
+            .entity(event.entity)
+            .remove::<Player>()
+            .insert((
+                PlayerDying {
+                    timer: Timer::from_seconds(PLAYER_DEATH_DURATION, TimerMode::Once),
+                },
+                Invulnerable,
+                RigidBodyDisabled,
+            ));
+    }
+}
+
+/// Spins and shrinks the fallen hero, then respawns it or lets the game end
+fn play_player_death(
+    mut commands: Commands,
+    mut dying_query: Query<(Entity, &mut Transform, &mut PlayerDying)>,
+    mut respawn: ResMut<RespawnState>,
+    settings: Res<RespawnSettings>,
+    checkpoints: Res<Checkpoints>,
+    current_level: Res<CurrentLevel>,
+    mut unit_query: Query<(Entity, &mut Unit), With<LevelEntity>>,
+    mut hp_events: EventWriter<HpChangeEvent>,
+    mut transition_events: EventWriter<LevelTransitionEvent>,
+    mut respawned_events: EventWriter<PlayerRespawnedEvent>,
+    mut global_entities: ResMut<GlobalEntityMap>,
+    time: Res<Time>,
+) {
+    for (entity, mut transform, mut dying) in dying_query.iter_mut() {
+        dying.timer.tick(time.delta());
+        let progress = dying.timer.fraction();
+        transform.scale = Vec3::splat(1.0 - progress);
+        transform.rotate_z(time.delta_secs() * 12.0);
+        if !dying.timer.finished() {
+            continue;
+        }
+
+        global_entities.remove_unit(entity);
+        commands.entity(entity).despawn();
+        if respawn.lives == 0 {
+            // Without a player the game state moves to game over
+            info!("No lives left");
+            continue;
+        }
+        respawn.lives -= 1;
+        let position = respawn_position(&respawn, &checkpoints);
+        info!("Respawning at {:?}, {} lives left", position, respawn.lives);
+        let hero = spawn_hero(
+            &mut commands,
+            position,
+            settings.hp_fraction,
+            respawn.experience,
+        );
+        respawned_events.write(PlayerRespawnedEvent {
+            entity: hero,
+            position,
+        });
+
+        match settings.enemy_reset {
+            EnemyReset::Keep => {}
+            EnemyReset::Heal => {
+                for (unit_entity, mut unit) in unit_query.iter_mut() {
+                    let missing = unit.max_hp - unit.hp;
+                    if missing > 0.0 && !unit.is_dead() {
+                        unit.heal(missing, unit_entity, unit_entity, &mut hp_events);
+                    }
+                }
+            }
+            EnemyReset::Respawn => {
+                // The hero already exists, so the reload keeps it at the respawn point
+                transition_events.write(LevelTransitionEvent {
+                    map: current_level.map.clone(),
+                    entry: None,
+                });
+            }
+        }
+    }
+}
+
+fn respawn_position(respawn: &RespawnState, checkpoints: &Checkpoints) -> Vec2 {
+    respawn
+        .checkpoint
+        .as_ref()
+        .and_then(|name| checkpoints.points.get(name))
+        .copied()
+        .unwrap_or(respawn.level_start)
+}
+
+fn spawn_hero(
+    commands: &mut Commands,
+    position: Vec2,
+    hp_fraction: f32,
+    experience: Option<Experience>,
+) -> Entity {
+    let mut hero = commands.spawn_unit("hero", position);
+    hero.insert((hero_bundle(), Respawned { hp_fraction }));
+    if let Some(experience) = experience {
+        hero.insert(experience);
+    }
+    hero.id()
+}
+
+/// Sets the respawned hero's HP; the event also points the health bar at the new entity
+fn apply_respawn_penalty(
+    mut commands: Commands,
+    mut player_query: Query<(Entity, &mut Unit, &Respawned)>,
+    mut hp_events: EventWriter<HpChangeEvent>,
+) {
+    for (entity, mut unit, respawned) in player_query.iter_mut() {
+        let old_hp = unit.hp;
+        unit.hp = (unit.max_hp * respawned.hp_fraction).max(1.0);
+        hp_events.write(HpChangeEvent {
+            entity,
+            source: entity,
+            old_hp,
+            new_hp: unit.hp,
+            max_hp: unit.max_hp,
+            change_type: HpChangeType::SetValue,
+        });
+        commands.entity(entity).remove::<Respawned>();
+    }
+}
+
+/// Spends a continue to come back at the last checkpoint with full lives
+fn continue_game(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut respawn: ResMut<RespawnState>,
+    settings: Res<RespawnSettings>,
+    checkpoints: Res<Checkpoints>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut respawned_events: EventWriter<PlayerRespawnedEvent>,
+) {
+    if respawn.continues == 0 || !keyboard_input.just_pressed(KeyCode::KeyC) {
+        return;
+    }
+    respawn.continues -= 1;
+    respawn.lives = settings.lives;
+    let position = respawn_position(&respawn, &checkpoints);
+    info!("Continuing at {:?}, {} continues left", position, respawn.continues);
+    let hero = spawn_hero(&mut commands, position, 1.0, respawn.experience);
+    respawned_events.write(PlayerRespawnedEvent {
+        entity: hero,
+        position,
+    });
+    next_state.set(GameState::Playing);
+}
+
+fn setup_lives_ui(mut commands: Commands) {
+    commands.spawn((
+        Text::new(""),
+        TextFont {
+            font_size: 20.0,
+            ..default()
+        },
+        TextColor(Color::WHITE),
+        Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(52.0),
+            left: Val::Px(20.0),
+            ..default()
+        },
+        LivesText,
+    ));
+}
+
+fn update_lives_ui(respawn: Res<RespawnState>, mut lives_text: Query<&mut Text, With<LivesText>>) {
+    if !respawn.is_changed() {
+        return;
+    }
+    if let Ok(mut text) = lives_text.single_mut() {
+        text.0 = format!("Lives {}", respawn.lives);
+    }
+}
+
+fn spawn_continue_text(mut commands: Commands, respawn: Res<RespawnState>) {
+    if respawn.continues == 0 {
+        return;
+    }
+    commands.spawn((
+        Text::new(format!("Press C to continue ({} left)", respawn.continues)),
+        TextFont {
+            font_size: 24.0,
+            ..default()
+        },
+        TextColor(Color::srgb(1.0, 0.84, 0.0)),
+        Node {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(120.0),
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Center),
+        ContinueText,
+    ));
+}
+
+fn despawn_continue_text(mut commands: Commands, text_query: Query<Entity, With<ContinueText>>) {
+    for entity in text_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
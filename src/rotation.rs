@@ -1,4 +1,5 @@
 use crate::{ai::TargetDetector, custom_move::PlayerMove, global_entity_map::GlobalEntityMap};
+use crate::game_state::GameplaySet;
 use bevy::prelude::*;
 use ordered_float::Float;
 
@@ -6,7 +7,7 @@ pub struct RotationPlugin;
 
 impl Plugin for RotationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, facing_target);
+        app.add_systems(Update, facing_target.in_set(GameplaySet));
     }
 }
 
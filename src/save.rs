@@ -0,0 +1,390 @@
+use crate::Player;
+use crate::berserker::Berserker;
+use crate::constants::*;
+use crate::difficulty::Difficulty;
+use crate::experience::Experience;
+use crate::game_state::GameplaySet;
+use crate::level::level::CurrentLevel;
+use crate::respawn::{Checkpoints, RespawnState};
+use crate::stats::Stats;
+use crate::unit::{HpChangeEvent, HpChangeType, Unit};
+use crate::unit_death::UnitDiedEvent;
+use crate::weapon::{EquippedWeapon, WeaponKind};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Version written into new saves. Bump it when the layout changes and teach
+/// `parse_save` to upgrade the previous version.
+pub const SAVE_VERSION: u32 = 1;
+
+/// Everything written to the save file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveData {
+    pub version: u32,
+    pub player: PlayerSave,
+    pub level: LevelSave,
+    /// Ids of unique enemies that were killed, see `UniqueEnemy`
+    #[serde(default)]
+    pub killed_uniques: Vec<String>,
+    #[serde(default)]
+    pub settings: SettingsSave,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlayerSave {
+    pub hp: f32,
+    pub max_hp: f32,
+    pub berserker_level: i32,
+    pub weapon: WeaponKind,
+    #[serde(default = "default_level")]
+    pub level: u32,
+    #[serde(default)]
+    pub xp: u32,
+}
+
+fn default_level() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LevelSave {
+    pub map: String,
+    pub checkpoint: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SettingsSave {
+    pub difficulty: Difficulty,
+}
+
+/// Just the version, read first to pick how the rest of the file is parsed
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Parse(String),
+    /// Written by a newer build of the game
+    NewerVersion(u32),
+    /// Older than any layout this build can upgrade
+    UnknownVersion(u32),
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(err: std::io::Error) -> Self {
+        SaveError::Io(err)
+    }
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SaveError::Io(err) => write!(f, "Could not access save file: {}", err),
+            SaveError::Parse(err) => write!(f, "Could not parse save file: {}", err),
+            SaveError::NewerVersion(version) => write!(
+                f,
+                "Save version {} is newer than supported version {}",
+                version, SAVE_VERSION
+            ),
+            SaveError::UnknownVersion(version) => write!(f, "Unknown save version {}", version),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+/// Enemy that stays dead once killed, identified by map and Tiled object id
+#[derive(Component, Debug, Clone)]
+pub struct UniqueEnemy(pub String);
+
+impl UniqueEnemy {
+    pub fn new(map: &str, object_id: u32) -> Self {
+        Self(format!("{}#{}", map, object_id))
+    }
+}
+
+/// Save progress of the running game
+#[derive(Resource, Default)]
+pub struct SaveState {
+    /// Save read at startup, applied to the hero once its level is loaded
+    pub restore: Option<SaveData>,
+    pub killed_uniques: HashSet<String>,
+    /// Set when progress that isn't tied to a checkpoint should be saved
+    dirty: bool,
+    /// Map and checkpoint written by the last save
+    last_saved: Option<(String, Option<String>)>,
+}
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SaveState>()
+            .add_systems(Startup, load_save)
+            .add_systems(
+                Update,
+                (record_unique_kills, restore_player, autosave)
+                    .chain()
+                    .in_set(GameplaySet),
+            );
+    }
+}
+
+/// `save.ron` in the platform's user data directory
+pub fn save_path() -> Option<PathBuf> {
+    let data_dir = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+    };
+    data_dir.map(|dir| dir.join(SAVE_DIR_NAME).join(SAVE_FILE_NAME))
+}
+
+/// Reads a save of any supported version, upgrading older layouts to the current one
+pub fn parse_save(text: &str) -> Result<SaveData, SaveError> {
+    let header: SaveHeader = ron::from_str(text).map_err(|err| SaveError::Parse(err.to_string()))?;
+    match header.version {
+        // Older versions get an arm here that parses their layout and converts it forward
+        SAVE_VERSION => ron::from_str(text).map_err(|err| SaveError::Parse(err.to_string())),
+        version if version > SAVE_VERSION => Err(SaveError::NewerVersion(version)),
+        version => Err(SaveError::UnknownVersion(version)),
+    }
+}
+
+pub fn read_save(path: &Path) -> Result<Option<SaveData>, SaveError> {
+    match fs::read_to_string(path) {
+        Ok(text) => parse_save(&text).map(Some),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Writes next to the save and renames over it, so a crash mid-write never leaves a torn file
+pub fn write_save(path: &Path, data: &SaveData) -> Result<(), SaveError> {
+    let text = ron::ser::to_string_pretty(data, ron::ser::PrettyConfig::default())
+        .map_err(|err| SaveError::Parse(err.to_string()))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let temp_path = path.with_extension("ron.tmp");
+    let mut file = fs::File::create(&temp_path)?;
+    file.write_all(text.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+fn load_save(
+    mut save_state: ResMut<SaveState>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut difficulty: ResMut<Difficulty>,
+) {
+    let Some(path) = save_path() else {
+        warn!("No user data directory, saving is disabled");
+        return;
+    };
+    let data = match read_save(&path) {
+        Ok(Some(data)) => data,
+        Ok(None) => {
+            info!("No save at {}", path.display());
+            return;
+        }
+        Err(err) => {
+            error!("{}: {}", path.display(), err);
+            return;
+        }
+    };
+
+    info!("Loaded save from {} at {}", path.display(), data.level.map);
+    *difficulty = data.settings.difficulty;
+    *current_level = CurrentLevel::new(data.level.map.clone());
+    save_state.killed_uniques = data.killed_uniques.iter().cloned().collect();
+    save_state.last_saved = Some((data.level.map.clone(), data.level.checkpoint.clone()));
+    save_state.restore = Some(data);
+}
+
+fn record_unique_kills(
+    mut died_events: EventReader<UnitDiedEvent>,
+    unique_query: Query<&UniqueEnemy>,
+    mut save_state: ResMut<SaveState>,
+) {
+    for event in died_events.read() {
+        if let Ok(unique) = unique_query.get(event.entity) {
+            info!("Unique enemy {} killed", unique.0);
+            save_state.killed_uniques.insert(unique.0.clone());
+            save_state.dirty = true;
+        }
+    }
+}
+
+/// Gives the hero its saved stats and moves it to the saved checkpoint.
+///
+/// The weapon is applied when the hero spawns, see `spawn_entities_from_objects`. Waits for
+/// the hero to be built from its template so the level modifiers have stats to apply to.
+fn restore_player(
+    mut save_state: ResMut<SaveState>,
+    mut respawn: ResMut<RespawnState>,
+    checkpoints: Res<Checkpoints>,
+    mut player_query: Query<
+        (Entity, &mut Unit, &mut Berserker, &mut Experience, &mut Transform),
+        (With<Player>, With<Stats>),
+    >,
+    mut hp_events: EventWriter<HpChangeEvent>,
+) {
+    let Some(data) = &save_state.restore else {
+        return;
+    };
+    // Wait for the respawn tracking to settle on the saved map
+    if respawn.map() != data.level.map {
+        return;
+    }
+    let Ok((entity, mut unit, mut berserker, mut experience, mut transform)) =
+        player_query.single_mut()
+    else {
+        return;
+    };
+
+    // The level change recomputes max HP through the stat modifiers, keeping the HP ratio
+    *experience = Experience {
+        level: data.player.level,
+        xp: data.player.xp,
+    };
+    let old_hp = unit.hp;
+    unit.max_hp = data.player.max_hp;
+    unit.hp = data.player.hp.clamp(1.0, unit.max_hp);
+    berserker.level = data.player.berserker_level;
+    hp_events.write(HpChangeEvent {
+        entity,
+        source: entity,
+        old_hp,
+        new_hp: unit.hp,
+        max_hp: unit.max_hp,
+        change_type: HpChangeType::SetValue,
+    });
+
+    if let Some(checkpoint) = &data.level.checkpoint {
+        match checkpoints.points.get(checkpoint) {
+            Some(position) => {
+                transform.translation.x = position.x;
+                transform.translation.y = position.y;
+                respawn.set_checkpoint(checkpoint.clone());
+            }
+            None => warn!("Saved checkpoint '{}' not found in {}", checkpoint, data.level.map),
+        }
+    }
+    info!("Restored player from save");
+    save_state.restore = None;
+}
+
+/// Saves when the player reaches a new level or checkpoint, kills a unique enemy, or presses F5
+fn autosave(
+    mut save_state: ResMut<SaveState>,
+    respawn: Res<RespawnState>,
+    difficulty: Res<Difficulty>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    player_query: Query<(&Unit, &Berserker, &Experience, Option<&EquippedWeapon>), With<Player>>,
+) {
+    if save_state.restore.is_some() || respawn.map().is_empty() {
+        return;
+    }
+    let progress = (respawn.map().to_string(), respawn.checkpoint().map(str::to_string));
+    let moved_on = save_state.last_saved.as_ref() != Some(&progress);
+    if !moved_on && !save_state.dirty && !keyboard_input.just_pressed(KeyCode::F5) {
+        return;
+    }
+    let Ok((unit, berserker, experience, weapon)) = player_query.single() else {
+        return;
+    };
+
+    let mut killed_uniques: Vec<String> = save_state.killed_uniques.iter().cloned().collect();
+    killed_uniques.sort();
+    let data = SaveData {
+        version: SAVE_VERSION,
+        player: PlayerSave {
+            hp: unit.hp,
+            max_hp: unit.max_hp,
+            berserker_level: berserker.level,
+            weapon: weapon.map_or(WeaponKind::Unarmed, |weapon| weapon.0),
+            level: experience.level,
+            xp: experience.xp,
+        },
+        level: LevelSave {
+            map: progress.0.clone(),
+            checkpoint: progress.1.clone(),
+        },
+        killed_uniques,
+        settings: SettingsSave {
+            difficulty: *difficulty,
+        },
+    };
+
+    save_state.dirty = false;
+    save_state.last_saved = Some(progress);
+    let Some(path) = save_path() else {
+        return;
+    };
+    match write_save(&path, &data) {
+        Ok(()) => info!("Saved game to {}", path.display()),
+        Err(err) => error!("{}: {}", path.display(), err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn save() -> SaveData {
+        SaveData {
+            version: SAVE_VERSION,
+            player: PlayerSave {
+                hp: 42.0,
+                max_hp: 100.0,
+                berserker_level: 1,
+                weapon: WeaponKind::Axe,
+                level: 3,
+                xp: 25,
+            },
+            level: LevelSave {
+                map: "map/dungeon.tmx".to_string(),
+                checkpoint: Some("Gate".to_string()),
+            },
+            killed_uniques: vec!["map/level.tmx#5".to_string()],
+            settings: SettingsSave {
+                difficulty: Difficulty::Hard,
+            },
+        }
+    }
+
+    #[test]
+    fn save_round_trips_through_disk() {
+        let path = std::env::temp_dir()
+            .join(format!("character_2d_save_test_{}", std::process::id()))
+            .join(SAVE_FILE_NAME);
+        write_save(&path, &save()).unwrap();
+        assert_eq!(read_save(&path).unwrap(), Some(save()));
+        assert!(!path.with_extension("ron.tmp").exists());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn rejects_saves_from_newer_versions() {
+        let text = ron::to_string(&SaveData {
+            version: SAVE_VERSION + 1,
+            ..save()
+        })
+        .unwrap();
+        assert!(matches!(parse_save(&text), Err(SaveError::NewerVersion(_))));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::constants::*;
+use crate::patrol::IdleBehavior;
+use crate::unit::UnitType;
+use crate::weapon::WeaponKind;
+use bevy::prelude::*;
+use tiled::{ObjectData, PropertyValue};
+
+/// Properties read by `IdleBehavior::from_properties`
+const IDLE_PROPERTIES: [&str; 3] = ["idle", "patrol_path", "wander_radius"];
+
+/// Per-object overrides for a spawn point, read from Tiled custom properties.
+///
+/// Anything left as `None` falls back to the template's default.
+#[derive(Debug, Default, Clone)]
+pub struct SpawnProperties {
+    /// Which spawn template to use instead of the object name
+    pub template: Option<String>,
+    pub name: Option<String>,
+    pub max_hp: Option<f32>,
+    /// Multiplier applied on top of the max HP, e.g. by wave difficulty ramps
+    pub hp_scale: Option<f32>,
+    pub speed: Option<f32>,
+    pub weapon: Option<WeaponKind>,
+    /// Unit type whose AI move set is used
+    pub ai_profile: Option<UnitType>,
+    pub force: Option<u32>,
+    pub idle: Option<IdleBehavior>,
+    /// Stays dead once killed, across levels and saves
+    pub unique: bool,
+}
+
+impl SpawnProperties {
+    pub fn from_object(object: &ObjectData) -> Self {
+        let mut spawn = Self::default();
+        if IDLE_PROPERTIES
+            .iter()
+            .any(|key| object.properties.contains_key(*key))
+        {
+            spawn.idle = Some(IdleBehavior::from_properties(&object.properties));
+        }
+
+        for (key, value) in object.properties.iter() {
+            match key.as_str() {
+                "template" => spawn.template = read_string(object, key, value),
+                "name" => spawn.name = read_string(object, key, value),
+                "max_hp" => spawn.max_hp = read_f32(object, key, value),
+                "hp_scale" => spawn.hp_scale = read_f32(object, key, value),
+                "speed" => spawn.speed = read_f32(object, key, value),
+                "weapon" => {
+                    spawn.weapon = read_string(object, key, value).and_then(|weapon| {
+                        let kind = WeaponKind::from_name(&weapon);
+                        if kind.is_none() {
+                            warn!("Object {}: unknown weapon '{}'", object.id(), weapon);
+                        }
+                        kind
+                    });
+                }
+                "ai_profile" => spawn.ai_profile = read_string(object, key, value).map(UnitType::new),
+                "force" => spawn.force = read_force(object, value),
+                "unique" => spawn.unique = read_bool(object, key, value).unwrap_or(false),
+                key if IDLE_PROPERTIES.contains(&key) => {}
+                _ => {
+                    warn!(
+                        "Object {} ('{}'): unknown spawn property '{}'",
+                        object.id(),
+                        object.name,
+                        key
+                    );
+                }
+            }
+        }
+
+        spawn
+    }
+
+    /// Template to spawn, defaulting to the object name
+    pub fn template_or<'a>(&'a self, object_name: &'a str) -> &'a str {
+        self.template.as_deref().unwrap_or(object_name)
+    }
+}
+
+fn read_string(object: &ObjectData, key: &str, value: &PropertyValue) -> Option<String> {
+    match value {
+        PropertyValue::StringValue(value) => Some(value.clone()),
+        _ => {
+            warn!("Object {}: property '{}' should be a string", object.id(), key);
+            None
+        }
+    }
+}
+
+fn read_bool(object: &ObjectData, key: &str, value: &PropertyValue) -> Option<bool> {
+    match value {
+        PropertyValue::BoolValue(value) => Some(*value),
+        _ => {
+            warn!("Object {}: property '{}' should be a bool", object.id(), key);
+            None
+        }
+    }
+}
+
+fn read_f32(object: &ObjectData, key: &str, value: &PropertyValue) -> Option<f32> {
+    match value {
+        PropertyValue::FloatValue(value) => Some(*value),
+        PropertyValue::IntValue(value) => Some(*value as f32),
+        _ => {
+            warn!("Object {}: property '{}' should be a number", object.id(), key);
+            None
+        }
+    }
+}
+
+/// Accepts either a force number or one of "player", "enemy" and "neutral"
+fn read_force(object: &ObjectData, value: &PropertyValue) -> Option<u32> {
+    match value {
+        PropertyValue::IntValue(force) if *force >= 0 => Some(*force as u32),
+        PropertyValue::StringValue(force) => match force.as_str() {
+            "player" => Some(FORCE_PLAYER),
+            "enemy" => Some(FORCE_ENEMY),
+            "neutral" => Some(FORCE_NEUTRAL),
+            _ => {
+                warn!("Object {}: unknown force '{}'", object.id(), force);
+                None
+            }
+        },
+        _ => {
+            warn!("Object {}: property 'force' should be a name or a number", object.id());
+            None
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use crate::ai::{AI, AISuspended, TargetDetector};
+use crate::constants::*;
+use crate::custom_move::MoveRecoveryEvent;
+use crate::difficulty::Difficulty;
+use crate::game_state::GameplaySet;
+use crate::stun::Stun;
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::f32::consts::TAU;
+
+/// Membership in the squad coordinator. Only token holders may attack; the rest
+/// wait on a ring around the shared target.
+#[derive(Component, Default)]
+pub struct SquadMember {
+    pub has_token: bool,
+    /// Angle of the waiting slot around the target, in radians
+    pub slot_angle: f32,
+    /// How long this member has been waiting for a token
+    pub wait_time: f32,
+    /// How long this member has been holding its token
+    pub held_time: f32,
+}
+
+impl SquadMember {
+    fn release_token(&mut self) {
+        self.has_token = false;
+        self.wait_time = 0.0;
+        self.held_time = 0.0;
+    }
+
+    /// World position this member should wait at while it has no token
+    pub fn slot_position(&self, target_position: Vec2) -> Vec2 {
+        target_position + Vec2::from_angle(self.slot_angle) * SQUAD_RING_RADIUS
+    }
+}
+
+pub struct SquadPlugin;
+
+impl Plugin for SquadPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (release_attack_tokens, release_tokens_of_disabled).in_set(GameplaySet),
+        );
+    }
+}
+
+/// Hands out attack tokens per target and spreads waiting members around it.
+///
+/// Suspended members neither hold nor wait for tokens.
+pub fn assign_attack_tokens(
+    mut squad_query: Query<
+        (Entity, &TargetDetector, &Transform, &mut SquadMember),
+        (With<AI>, Without<AISuspended>),
+    >,
+    target_query: Query<&Transform>,
+    difficulty: Res<Difficulty>,
+    time: Res<Time>,
+) {
+    let max_tokens = difficulty.settings().attack_tokens;
+    let mut squads: HashMap<Entity, Vec<Entity>> = HashMap::new();
+
+    for (entity, detector, _, mut member) in squad_query.iter_mut() {
+        if detector.target == Entity::PLACEHOLDER {
+            // Give the token back as soon as the target is lost
+            if member.has_token {
+                member.release_token();
+            }
+            member.wait_time = 0.0;
+            continue;
+        }
+        if member.has_token {
+            // Take the token back from holders that never reach recovery
+            member.held_time += time.delta_secs();
+            if member.held_time > ATTACK_TOKEN_TIMEOUT {
+                debug!(
+                    "Entity {:?} held its attack token too long, releasing",
+                    entity
+                );
+                member.release_token();
+            }
+        }
+        squads.entry(detector.target).or_default().push(entity);
+    }
+
+    for (target, members) in squads {
+        let Ok(target_transform) = target_query.get(target) else {
+            continue;
+        };
+        let target_position = target_transform.translation.xy();
+
+        let mut holders = 0;
+        let mut waiting: Vec<(Entity, f32, f32)> = Vec::new();
+        for entity in members.iter() {
+            if let Ok((_, _, transform, mut member)) = squad_query.get_mut(*entity) {
+                if member.has_token {
+                    holders += 1;
+                } else {
+                    member.wait_time += time.delta_secs();
+                    let offset = transform.translation.xy() - target_position;
+                    waiting.push((*entity, member.wait_time, offset.y.atan2(offset.x)));
+                }
+            }
+        }
+
+        // Longest waiting members get the free tokens first
+        waiting.sort_by(|a, b| b.1.total_cmp(&a.1));
+        let free_tokens = max_tokens.saturating_sub(holders);
+        let (granted, still_waiting) = waiting.split_at(free_tokens.min(waiting.len()));
+        for (entity, wait_time, _) in granted {
+            if let Ok((_, _, _, mut member)) = squad_query.get_mut(*entity) {
+                debug!(
+                    "Entity {:?} granted attack token on {:?} after waiting {:.2}s",
+                    entity, target, wait_time
+                );
+                member.has_token = true;
+                member.wait_time = 0.0;
+                member.held_time = 0.0;
+            }
+        }
+
+        // Spread the remaining members evenly, keeping their current order around the target
+        let mut still_waiting = still_waiting.to_vec();
+        still_waiting.sort_by(|a, b| a.2.total_cmp(&b.2));
+        if let Some(&(_, _, base_angle)) = still_waiting.first() {
+            let step = TAU / still_waiting.len() as f32;
+            for (index, (entity, _, _)) in still_waiting.iter().enumerate() {
+                if let Ok((_, _, _, mut member)) = squad_query.get_mut(*entity) {
+                    member.slot_angle = base_angle + step * index as f32;
+                }
+            }
+        }
+    }
+}
+
+/// Returns the attack token once the holder's move reaches recovery
+fn release_attack_tokens(
+    mut recovery_events: EventReader<MoveRecoveryEvent>,
+    mut squad_query: Query<&mut SquadMember>,
+) {
+    for event in recovery_events.read() {
+        if let Ok(mut member) = squad_query.get_mut(event.actor) {
+            if member.has_token {
+                debug!(
+                    "Entity {:?} finished attack '{}', releasing token",
+                    event.actor, event.move_name
+                );
+                member.release_token();
+            }
+        }
+    }
+}
+
+/// Returns the attack token of members that get stunned or suspended before their move
+/// reaches recovery
+fn release_tokens_of_disabled(
+    mut squad_query: Query<(Entity, &mut SquadMember), Or<(Added<Stun>, Added<AISuspended>)>>,
+) {
+    for (entity, mut member) in squad_query.iter_mut() {
+        if member.has_token {
+            debug!("Entity {:?} was disabled, releasing attack token", entity);
+            member.release_token();
+        }
+    }
+}
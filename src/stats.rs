@@ -0,0 +1,207 @@
+use crate::game_state::GameplaySet;
+use crate::unit::Unit;
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum StatKind {
+    MaxHp,
+    /// Movement speed, the unit's own for AI and the current move's for the player
+    Speed,
+    /// Weapon damage
+    Damage,
+    /// Critical rate of the current move
+    CritRate,
+}
+
+/// How a modifier combines with the base value.
+///
+/// The final value is `(base + flat) * (1 + sum of percent-add) * product of (1 + percent-multiply)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModifierKind {
+    Flat,
+    /// Summed with the other percent-add modifiers, e.g. several +10% buffs make +30%
+    PercentAdd,
+    /// Applied on its own, e.g. two +20% stances make +44%
+    PercentMultiply,
+}
+
+/// What put a modifier on a unit, so it can be removed without touching the others
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModifierSource {
+    Level,
+    Berserker,
+    /// Slowed while swinging a weapon
+    Attacking,
+    Buff(String),
+    Equipment(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct StatModifier {
+    pub stat: StatKind,
+    pub kind: ModifierKind,
+    /// Amount for flat modifiers, fraction for percent ones (0.2 is +20%)
+    pub value: f32,
+    pub source: ModifierSource,
+    /// Removed once this runs out; `None` lasts until removed by its source
+    pub duration: Option<Timer>,
+}
+
+impl StatModifier {
+    pub fn new(stat: StatKind, kind: ModifierKind, value: f32, source: ModifierSource) -> Self {
+        Self {
+            stat,
+            kind,
+            value,
+            source,
+            duration: None,
+        }
+    }
+
+    pub fn flat(stat: StatKind, value: f32, source: ModifierSource) -> Self {
+        Self::new(stat, ModifierKind::Flat, value, source)
+    }
+
+    pub fn percent_add(stat: StatKind, value: f32, source: ModifierSource) -> Self {
+        Self::new(stat, ModifierKind::PercentAdd, value, source)
+    }
+
+    pub fn percent_multiply(stat: StatKind, value: f32, source: ModifierSource) -> Self {
+        Self::new(stat, ModifierKind::PercentMultiply, value, source)
+    }
+
+    pub fn with_duration(mut self, seconds: f32) -> Self {
+        self.duration = Some(Timer::from_seconds(seconds, TimerMode::Once));
+        self
+    }
+}
+
+/// Base stats of a unit and the modifiers stacked on them.
+///
+/// Stats whose base lives elsewhere, like weapon damage and move critical rates, have no
+/// base here and are read through `apply`.
+#[derive(Component, Debug, Clone, Default)]
+pub struct Stats {
+    base: HashMap<StatKind, f32>,
+    modifiers: Vec<StatModifier>,
+}
+
+impl Stats {
+    pub fn with_base(mut self, stat: StatKind, value: f32) -> Self {
+        self.base.insert(stat, value);
+        self
+    }
+
+    pub fn base(&self, stat: StatKind) -> f32 {
+        self.base.get(&stat).copied().unwrap_or(0.0)
+    }
+
+    pub fn set_base(&mut self, stat: StatKind, value: f32) {
+        self.base.insert(stat, value);
+    }
+
+    /// Modified value of a stat stored here
+    pub fn value(&self, stat: StatKind) -> f32 {
+        self.apply(stat, self.base(stat))
+    }
+
+    /// Applies the modifiers of `stat` to a base value from somewhere else
+    pub fn apply(&self, stat: StatKind, base: f32) -> f32 {
+        let mut flat = 0.0;
+        let mut percent_add = 0.0;
+        let mut multiplier = 1.0;
+        for modifier in self.modifiers.iter().filter(|modifier| modifier.stat == stat) {
+            match modifier.kind {
+                ModifierKind::Flat => flat += modifier.value,
+                ModifierKind::PercentAdd => percent_add += modifier.value,
+                ModifierKind::PercentMultiply => multiplier *= 1.0 + modifier.value,
+            }
+        }
+        (base + flat) * (1.0 + percent_add) * multiplier
+    }
+
+    pub fn add(&mut self, modifier: StatModifier) {
+        self.modifiers.push(modifier);
+    }
+
+    pub fn remove_source(&mut self, source: &ModifierSource) {
+        self.modifiers.retain(|modifier| modifier.source != *source);
+    }
+
+    pub fn has_source(&self, source: &ModifierSource) -> bool {
+        self.modifiers.iter().any(|modifier| modifier.source == *source)
+    }
+
+    /// Replaces every modifier from `source` with `modifiers`
+    pub fn set_source(&mut self, source: &ModifierSource, modifiers: impl IntoIterator<Item = StatModifier>) {
+        self.remove_source(source);
+        self.modifiers.extend(modifiers);
+    }
+}
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (expire_modifiers, sync_unit_stats).chain().in_set(GameplaySet),
+        );
+    }
+}
+
+fn expire_modifiers(mut stats_query: Query<&mut Stats>, time: Res<Time>) {
+    for mut stats in stats_query.iter_mut() {
+        // Only flag the stats as changed when a modifier actually runs out
+        let mut expired = false;
+        for modifier in stats.bypass_change_detection().modifiers.iter_mut() {
+            if let Some(duration) = &mut modifier.duration {
+                expired |= duration.tick(time.delta()).finished();
+            }
+        }
+        if expired {
+            stats.modifiers.retain(|modifier| {
+                !modifier
+                    .duration
+                    .as_ref()
+                    .is_some_and(|duration| duration.finished())
+            });
+        }
+    }
+}
+
+/// Copies max HP and speed onto `Unit`, keeping the HP ratio when max HP changes
+pub fn sync_unit_stats(mut unit_query: Query<(&Stats, &mut Unit), Changed<Stats>>) {
+    for (stats, mut unit) in unit_query.iter_mut() {
+        let max_hp = stats.value(StatKind::MaxHp);
+        if max_hp != unit.max_hp && max_hp > 0.0 {
+            let hp_ratio = unit.hp_percentage();
+            unit.max_hp = max_hp;
+            unit.hp = max_hp * hp_ratio;
+        }
+        unit.speed = stats.value(StatKind::Speed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modifier_layers_compose() {
+        let mut stats = Stats::default().with_base(StatKind::Speed, 100.0);
+        stats.add(StatModifier::flat(StatKind::Speed, 50.0, ModifierSource::Berserker));
+        stats.add(StatModifier::percent_add(StatKind::Speed, 0.1, ModifierSource::Level));
+        stats.add(StatModifier::percent_add(StatKind::Speed, 0.1, ModifierSource::Buff("haste".into())));
+        stats.add(StatModifier::percent_multiply(StatKind::Speed, -0.2, ModifierSource::Attacking));
+        // (100 + 50) * 1.2 * 0.8
+        assert!((stats.value(StatKind::Speed) - 144.0).abs() < 1e-4);
+        // Other stats are untouched
+        assert_eq!(stats.apply(StatKind::Damage, 10.0), 10.0);
+
+        stats.remove_source(&ModifierSource::Attacking);
+        assert!((stats.value(StatKind::Speed) - 180.0).abs() < 1e-4);
+    }
+}
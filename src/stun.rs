@@ -1,3 +1,4 @@
+use crate::game_state::GameplaySet;
 use bevy::{
     prelude::*,
     reflect::TypePath,
@@ -75,7 +76,7 @@ pub struct StunPlugin;
 impl Plugin for StunPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(Material2dPlugin::<StunMaterial>::default())
-            .add_systems(Update, (update_stun_effects, apply_stun_shader));
+            .add_systems(Update, (update_stun_effects, apply_stun_shader).in_set(GameplaySet));
     }
 }
 
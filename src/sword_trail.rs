@@ -4,6 +4,7 @@
 //! The trail follows the entity's movement and creates a ribbon-like effect that
 //! resembles a sword slash or magical weapon trail.
 
+use crate::game_state::GameplaySet;
 use bevy::color::palettes::basic::*;
 use bevy::prelude::*;
 use bevy_hanabi::prelude::*;
@@ -14,7 +15,7 @@ pub struct SwordTrailPlugin;
 impl Plugin for SwordTrailPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(HanabiPlugin);
-        app.add_systems(Update, (spawn_sword_trails, despawn_sword_trails));
+        app.add_systems(Update, (spawn_sword_trails, despawn_sword_trails).in_set(GameplaySet));
     }
 }
 
@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use serde::Deserialize;
 
 use crate::{berserker::BerserkerHealEvent, constants::*};
 
@@ -10,13 +11,37 @@ impl Plugin for UnitPlugin {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum UnitType {
-    Hero,
-    SwordMan,
-    Dummy,
+/// Open identifier for a kind of unit, e.g. "swordman". Matches the `unit_type` of
+/// unit templates and keys per-type data such as AI move sets.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub struct UnitType(pub String);
+
+impl UnitType {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Default for UnitType {
+    fn default() -> Self {
+        Self::new(UNIT_TYPE_DUMMY)
+    }
 }
 
+impl std::fmt::Display for UnitType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Hits against this unit are ignored
+#[derive(Component)]
+pub struct Invulnerable;
+
 #[derive(Component)]
 pub struct Unit {
     pub name: String,
@@ -148,7 +173,7 @@ impl Default for UnitBuilder {
             hp: DEFAULT_MAX_HP,
             max_hp: DEFAULT_MAX_HP,
             speed: DEFAULT_SPEED,
-            unit_type: UnitType::Dummy,
+            unit_type: UnitType::default(),
         }
     }
 }
@@ -164,6 +189,11 @@ impl UnitBuilder {
         self
     }
 
+    pub fn speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
     pub fn unitType(mut self, unit_type: UnitType) -> Self {
         self.unit_type = unit_type;
         self
@@ -1,30 +1,135 @@
-use crate::unit::HpChangeEvent;
+use crate::Player;
+use crate::ai::AISuspended;
+use crate::constants::*;
+use crate::custom_move::{Move, MoveRecoveryEvent, PlayerMove};
+use crate::game_state::GameplaySet;
+use crate::global_entity_map::GlobalEntityMap;
+use crate::particle::ParticleMaterialAsset;
+use crate::respawn::PlayerDying;
+use crate::unit::{HpChangeEvent, Invulnerable, Unit, UnitType};
 use bevy::prelude::*;
+use bevy_enoki::prelude::*;
+use bevy_rapier2d::prelude::*;
+use bevy_tweening::{lens::TransformScaleLens, *};
+use std::time::Duration;
 
 pub struct UnitDeathPlugin;
 
 impl Plugin for UnitDeathPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, handle_unit_death);
+        app.add_event::<UnitDiedEvent>().add_systems(
+            Update,
+            (handle_unit_death, despawn_dead_units).chain().in_set(GameplaySet),
+        );
     }
 }
 
-fn handle_unit_death(mut commands: Commands, mut hp_events: EventReader<HpChangeEvent>) {
+/// Sent once when a unit's HP reaches zero, for XP, loot and score
+#[derive(Event, Debug, Clone)]
+pub struct UnitDiedEvent {
+    pub entity: Entity,
+    /// Source of the killing blow
+    pub killer: Entity,
+    pub unit_type: UnitType,
+}
+
+/// Unit playing its death animation, out of the fight until it is despawned
+#[derive(Component)]
+pub struct Dying {
+    timer: Timer,
+}
+
+fn handle_unit_death(
+    mut commands: Commands,
+    mut hp_events: EventReader<HpChangeEvent>,
+    mut died_events: EventWriter<UnitDiedEvent>,
+    mut recovery_events: EventWriter<MoveRecoveryEvent>,
+    unit_query: Query<(&Unit, &Transform), Without<Dying>>,
+    player_query: Query<(), Or<(With<Player>, With<PlayerDying>)>>,
+    move_query: Query<&Move>,
+    global_entities: Res<GlobalEntityMap>,
+    asset_server: Res<AssetServer>,
+    material: Res<ParticleMaterialAsset>,
+) {
     for event in hp_events.read() {
         debug!(
             "Received HpChangeEvent for entity: {:?}, new_hp: {}",
             event.entity, event.new_hp
         );
 
-        // Check if the unit's HP has reached zero or below
-        if event.new_hp <= 0.0 {
-            info!(
-                "Unit {:?} has died (HP: {}), despawning entity",
-                event.entity, event.new_hp
-            );
+        // The player has its own death flow in respawn.rs
+        if event.new_hp > 0.0 || player_query.contains(event.entity) {
+            continue;
+        }
+        // Already dying units can still be hit for 0 HP
+        let Ok((unit, transform)) = unit_query.get(event.entity) else {
+            continue;
+        };
+        info!("Unit {:?} ({}) has died, killed by {:?}", event.entity, unit.name, event.source);
+
+        // Stop the swing in progress so its collider and trail are switched off
+        if let Some(&weapon) = global_entities.player_weapon.get(&event.entity) {
+            if let Ok(current) = move_query.get(weapon) {
+                recovery_events.write(MoveRecoveryEvent {
+                    actor: event.entity,
+                    move_name: current.move_metadata.name.clone(),
+                });
+            }
+            commands.entity(weapon).remove::<Move>();
+        }
+        if let Some(&collider) = global_entities.player_to_collider.get(&event.entity) {
+            commands.entity(collider).insert(ColliderDisabled);
+        }
+
+        let scale_tween = Tween::new(
+            EaseFunction::QuadraticIn,
+            Duration::from_secs_f32(UNIT_DEATH_DURATION),
+            TransformScaleLens {
+                start: transform.scale,
+                end: Vec3::ZERO,
+            },
+        );
+        commands
+            .entity(event.entity)
+            .remove::<PlayerMove>()
+            .insert((
+                Dying {
+                    timer: Timer::from_seconds(UNIT_DEATH_DURATION, TimerMode::Once),
+                },
+                AISuspended,
+                Invulnerable,
+                RigidBodyDisabled,
+                ColliderDisabled,
+                Animator::new(scale_tween),
+            ));
+        commands.spawn((
+            ParticleEffectHandle(asset_server.load("hitten.ron")),
+            Transform::from_translation(transform.translation),
+            Name::new("DeathEffect"),
+            ParticleSpawner(material.0.clone()),
+            OneShot::Despawn,
+        ));
+
+        died_events.write(UnitDiedEvent {
+            entity: event.entity,
+            killer: event.source,
+            unit_type: unit.unit_type.clone(),
+        });
+    }
+}
 
-            // Despawn the entity
-            commands.entity(event.entity).despawn_recursive();
+fn despawn_dead_units(
+    mut commands: Commands,
+    mut dying_query: Query<(Entity, &mut Dying)>,
+    mut global_entities: ResMut<GlobalEntityMap>,
+    time: Res<Time>,
+) {
+    for (entity, mut dying) in dying_query.iter_mut() {
+        if dying.timer.tick(time.delta()).finished() {
+            debug!("Despawning dead unit {:?}", entity);
+            global_entities.remove_unit(entity);
+            // The weapon is a child and goes with the unit
+            commands.entity(entity).despawn();
         }
     }
 }
@@ -0,0 +1,525 @@
+use crate::ai::{AI, AIOption, LockType, TargetDetector};
+use crate::boss::{Boss, BossPhase};
+use crate::collider::DynamicPhysicsBundle;
+use crate::constants::*;
+use crate::damage::Resistances;
+use crate::defense::DefensiveReaction;
+use crate::experience::XpReward;
+use crate::force::Force;
+use crate::global_entity_map::GlobalEntityMap;
+use crate::loot::Loot;
+use crate::patrol::{IdleBehavior, IdleState, SpawnAnchor};
+use crate::spawn_properties::SpawnProperties;
+use crate::squad::SquadMember;
+use crate::stats::{StatKind, Stats};
+use crate::unit::{Unit, UnitType};
+use crate::weapon::{EquippedWeapon, WeaponKind, equip_weapon};
+use bevy::asset::{AssetLoader, LoadContext, LoadedFolder, io::Reader};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Everything needed to build a unit, loaded from `assets/units/<id>.unit.ron`
+#[derive(Asset, TypePath, Deserialize, Clone, Debug)]
+pub struct UnitTemplate {
+    pub name: String,
+    pub unit_type: UnitType,
+    pub visual: UnitVisual,
+    pub body: BodyShape,
+    pub stats: UnitStats,
+    #[serde(default)]
+    pub resistances: Resistances,
+    #[serde(default)]
+    pub weapon: Option<WeaponTemplate>,
+    #[serde(default)]
+    pub targeting: Targeting,
+    /// Units without an AI profile are driven by something else, e.g. the player
+    #[serde(default)]
+    pub ai: Option<AiProfile>,
+    pub force: u32,
+    /// Loot table id from `assets/loot/<id>.loot.ron`, rolled on death
+    #[serde(default)]
+    pub loot: Option<String>,
+    /// Units with boss phases get the boss health bar and lock the arena they stand in
+    #[serde(default)]
+    pub boss: Option<BossProfile>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct UnitVisual {
+    pub shape: VisualShape,
+    /// sRGB; values above 1.0 glow with bloom
+    pub color: (f32, f32, f32),
+    pub z: f32,
+    #[serde(default)]
+    pub eyes: Vec<Eye>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub enum VisualShape {
+    Circle { radius: f32 },
+    Rect { width: f32, height: f32 },
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Eye {
+    pub offset: (f32, f32),
+    pub radius: f32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub enum BodyShape {
+    Ball { radius: f32 },
+    Box { half_width: f32, half_height: f32 },
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct UnitStats {
+    pub max_hp: f32,
+    #[serde(default = "default_speed")]
+    pub speed: f32,
+    /// Experience granted to the unit's killer
+    #[serde(default)]
+    pub xp: u32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct WeaponTemplate {
+    pub kind: WeaponKind,
+    pub offset: (f32, f32, f32),
+    pub scale: f32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Targeting {
+    pub alert_range: f32,
+    pub dis_alert_range: f32,
+    pub lock: LockType,
+}
+
+impl Default for Targeting {
+    fn default() -> Self {
+        Self {
+            alert_range: ALERT_RANGE,
+            dis_alert_range: DIS_ALERT_RANGE,
+            lock: LockType::Lock,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct AiProfile {
+    /// Moves to cycle through; empty uses the move set registered for the unit type
+    #[serde(default)]
+    pub moves: Vec<AiMove>,
+    /// Guard, wander or patrol around the spawn point while there is no target
+    #[serde(default = "default_true")]
+    pub idle: bool,
+    /// Share attack tokens with other units on the same target
+    #[serde(default)]
+    pub squad: bool,
+    /// React to incoming attacks with sidesteps, guards and reflects
+    #[serde(default)]
+    pub defensive: bool,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct AiMove {
+    pub name: String,
+    pub range: f32,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct BossProfile {
+    /// Ordered from full HP down; the first phase should have a threshold of 1.0
+    pub phases: Vec<BossPhaseTemplate>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct BossPhaseTemplate {
+    pub name: String,
+    /// Fraction of max HP at which the phase starts
+    pub hp_threshold: f32,
+    pub moves: Vec<AiMove>,
+    #[serde(default = "default_speed")]
+    pub speed: f32,
+}
+
+impl BossPhaseTemplate {
+    fn to_phase(&self) -> BossPhase {
+        BossPhase {
+            name: self.name.clone(),
+            hp_threshold: self.hp_threshold,
+            moves: self
+                .moves
+                .iter()
+                .map(|ai_move| AIOption::new(ai_move.name.clone(), ai_move.range))
+                .collect(),
+            speed: self.speed,
+        }
+    }
+}
+
+fn default_speed() -> f32 {
+    DEFAULT_SPEED
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Template handles keyed by template id, the file name without `.unit.ron`
+#[derive(Resource, Default)]
+pub struct UnitTemplates {
+    pub templates: HashMap<String, Handle<UnitTemplate>>,
+    /// Every template in `assets/units`, indexed into `templates` once it has loaded
+    folder: Handle<LoadedFolder>,
+    indexed: bool,
+}
+
+/// Typed handles of the assets in a loaded folder, keyed by file name without `extension`
+pub fn index_folder<A: Asset>(
+    folder: &LoadedFolder,
+    extension: &str,
+) -> HashMap<String, Handle<A>> {
+    let suffix = format!(".{}", extension);
+    let mut assets = HashMap::new();
+    for handle in folder.handles.iter() {
+        let Some(path) = handle.path() else {
+            continue;
+        };
+        let Some(id) = path
+            .path()
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(&suffix))
+        else {
+            debug!("Skipping {}, not a .{} file", path, extension);
+            continue;
+        };
+        match handle.clone().try_typed::<A>() {
+            Ok(typed) => {
+                assets.insert(id.to_string(), typed);
+            }
+            Err(e) => warn!("Skipping {}: {}", path, e),
+        }
+    }
+    assets
+}
+
+/// Spawns requested before their template finished loading
+#[derive(Resource, Default)]
+pub struct PendingUnitSpawns {
+    pub requests: Vec<SpawnUnit>,
+}
+
+/// Builds a unit from a template onto an already reserved entity
+#[derive(Clone, Debug)]
+pub struct SpawnUnit {
+    pub entity: Entity,
+    pub template: String,
+    pub position: Vec2,
+    pub properties: SpawnProperties,
+}
+
+impl Command for SpawnUnit {
+    fn apply(self, world: &mut World) {
+        if let Err(error) = world.run_system_cached_with(spawn_unit_system, self) {
+            error!("Failed to run unit spawn: {}", error);
+        }
+    }
+}
+
+pub trait SpawnUnitExt {
+    /// Spawn a unit from the template with the given id
+    fn spawn_unit(&mut self, template: impl Into<String>, position: Vec2) -> EntityCommands<'_>;
+
+    /// Spawn a unit from a template, applying per-spawn overrides
+    fn spawn_unit_with(
+        &mut self,
+        template: impl Into<String>,
+        position: Vec2,
+        properties: SpawnProperties,
+    ) -> EntityCommands<'_>;
+}
+
+impl SpawnUnitExt for Commands<'_, '_> {
+    fn spawn_unit(&mut self, template: impl Into<String>, position: Vec2) -> EntityCommands<'_> {
+        self.spawn_unit_with(template, position, SpawnProperties::default())
+    }
+
+    fn spawn_unit_with(
+        &mut self,
+        template: impl Into<String>,
+        position: Vec2,
+        properties: SpawnProperties,
+    ) -> EntityCommands<'_> {
+        let entity = self.spawn_empty().id();
+        self.queue(SpawnUnit {
+            entity,
+            template: template.into(),
+            position,
+            properties,
+        });
+        self.entity(entity)
+    }
+}
+
+#[derive(Default)]
+pub struct UnitTemplateLoader;
+
+impl AssetLoader for UnitTemplateLoader {
+    type Asset = UnitTemplate;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        ron::de::from_bytes(&bytes).map_err(|e| {
+            std::io::Error::other(format!(
+                "Could not parse unit template {}: {}",
+                load_context.path().display(),
+                e
+            ))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["unit.ron"]
+    }
+}
+
+pub struct UnitTemplatePlugin;
+
+impl Plugin for UnitTemplatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<UnitTemplate>()
+            .register_asset_loader(UnitTemplateLoader)
+            .init_resource::<UnitTemplates>()
+            .init_resource::<PendingUnitSpawns>()
+            .add_systems(Startup, load_unit_templates)
+            .add_systems(Update, (index_unit_templates, retry_pending_spawns).chain());
+    }
+}
+
+fn load_unit_templates(mut templates: ResMut<UnitTemplates>, asset_server: Res<AssetServer>) {
+    templates.folder = asset_server.load_folder(UNIT_TEMPLATE_DIR);
+    info!("Loading unit templates from '{}'", UNIT_TEMPLATE_DIR);
+}
+
+/// Rebuilds the template index whenever the folder finishes loading or a file is added
+fn index_unit_templates(
+    mut folder_events: EventReader<AssetEvent<LoadedFolder>>,
+    mut templates: ResMut<UnitTemplates>,
+    folders: Res<Assets<LoadedFolder>>,
+) {
+    for event in folder_events.read() {
+        let (AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id }) = event
+        else {
+            continue;
+        };
+        if *id != templates.folder.id() {
+            continue;
+        }
+        let Some(folder) = folders.get(*id) else {
+            continue;
+        };
+        templates.templates = index_folder(folder, "unit.ron");
+        templates.indexed = true;
+        info!("Loaded {} unit templates", templates.templates.len());
+    }
+}
+
+fn retry_pending_spawns(
+    mut commands: Commands,
+    mut pending: ResMut<PendingUnitSpawns>,
+    templates: Res<UnitTemplates>,
+    template_assets: Res<Assets<UnitTemplate>>,
+) {
+    if pending.requests.is_empty() {
+        return;
+    }
+
+    let (ready, waiting): (Vec<_>, Vec<_>) = pending.requests.drain(..).partition(|request| {
+        templates
+            .templates
+            .get(&request.template)
+            .is_some_and(|handle| template_assets.contains(handle))
+    });
+    pending.requests = waiting;
+    for request in ready {
+        commands.queue(request);
+    }
+}
+
+fn spawn_unit_system(
+    In(request): In<SpawnUnit>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut global_map: ResMut<GlobalEntityMap>,
+    mut pending: ResMut<PendingUnitSpawns>,
+    templates: Res<UnitTemplates>,
+    template_assets: Res<Assets<UnitTemplate>>,
+) {
+    if commands.get_entity(request.entity).is_err() {
+        debug!("Entity for '{}' spawn is gone, skipping", request.template);
+        return;
+    }
+    if !templates.indexed {
+        debug!(
+            "Unit templates not loaded yet, deferring '{}' spawn",
+            request.template
+        );
+        pending.requests.push(request);
+        return;
+    }
+    let Some(handle) = templates.templates.get(&request.template) else {
+        error!("Unknown unit template '{}'", request.template);
+        commands.entity(request.entity).despawn();
+        return;
+    };
+    let Some(template) = template_assets.get(handle) else {
+        debug!("Unit template '{}' not loaded yet, deferring spawn", request.template);
+        pending.requests.push(request);
+        return;
+    };
+
+    let properties = &request.properties;
+    let name = properties.name.clone().unwrap_or(template.name.clone());
+    info!(
+        "Spawning {} from template '{}' at ({}, {})",
+        name, request.template, request.position.x, request.position.y
+    );
+
+    let visual = &template.visual;
+    let (r, g, b) = visual.color;
+    let mesh = match visual.shape {
+        VisualShape::Circle { radius } => meshes.add(Circle::new(radius)),
+        VisualShape::Rect { width, height } => meshes.add(Rectangle::new(width, height)),
+    };
+    let body = match template.body {
+        BodyShape::Ball { radius } => DynamicPhysicsBundle::new_ball(radius),
+        BodyShape::Box {
+            half_width,
+            half_height,
+        } => DynamicPhysicsBundle::new_box(half_width, half_height),
+    };
+    let eye_material = materials.add(Color::BLACK);
+    let max_hp = properties.max_hp.unwrap_or(template.stats.max_hp) * properties.hp_scale.unwrap_or(1.0);
+    let speed = properties.speed.unwrap_or(template.stats.speed);
+
+    commands
+        .entity(request.entity)
+        .insert((
+            Name::new(name.clone()),
+            Mesh2d(mesh),
+            MeshMaterial2d(materials.add(Color::srgb(r, g, b))),
+            Transform::from_translation(request.position.extend(visual.z)),
+            body,
+            Velocity::zero(),
+            Unit::builder()
+                .name(name.clone())
+                .max_hp(max_hp)
+                .speed(speed)
+                .unitType(template.unit_type.clone())
+                .build(),
+            Stats::default()
+                .with_base(StatKind::MaxHp, max_hp)
+                .with_base(StatKind::Speed, speed),
+            TargetDetector {
+                target: Entity::PLACEHOLDER,
+                alert_range: template.targeting.alert_range,
+                dis_alert_range: template.targeting.dis_alert_range,
+                lock_type: template.targeting.lock,
+            },
+            Force {
+                force: properties.force.unwrap_or(template.force),
+            },
+            template.resistances.clone(),
+        ))
+        .with_children(|parent| {
+            for eye in visual.eyes.iter() {
+                parent.spawn((
+                    Mesh2d(meshes.add(Circle::new(eye.radius))),
+                    MeshMaterial2d(eye_material.clone()),
+                    Transform::from_xyz(eye.offset.0, eye.offset.1, 0.1),
+                ));
+            }
+        });
+
+    if template.stats.xp > 0 {
+        commands.entity(request.entity).insert(XpReward(template.stats.xp));
+    }
+    if let Some(loot) = &template.loot {
+        commands.entity(request.entity).insert(Loot(loot.clone()));
+    }
+    if let Some(boss) = &template.boss {
+        let phases = boss.phases.iter().map(BossPhaseTemplate::to_phase).collect();
+        commands.entity(request.entity).insert(Boss::new(name, phases));
+    }
+
+    if let Some(ai) = &template.ai {
+        let moves = if ai.moves.is_empty() || properties.ai_profile.is_some() {
+            let unit_type = properties.ai_profile.as_ref().unwrap_or(&template.unit_type);
+            global_map
+                .unittype_aioptions
+                .get(unit_type)
+                .cloned()
+                .unwrap_or_else(|| {
+                    warn!("No AI moves registered for unit type '{}'", unit_type);
+                    Vec::new()
+                })
+        } else {
+            ai.moves
+                .iter()
+                .map(|ai_move| AIOption::new(ai_move.name.clone(), ai_move.range))
+                .collect()
+        };
+
+        let mut entity = commands.entity(request.entity);
+        entity.insert(AI::new(moves));
+        if ai.idle {
+            entity.insert((
+                properties.idle.clone().unwrap_or(IdleBehavior::Guard),
+                IdleState::default(),
+                SpawnAnchor {
+                    position: request.position,
+                },
+            ));
+        }
+        if ai.squad {
+            entity.insert(SquadMember::default());
+        }
+        if ai.defensive {
+            entity.insert(DefensiveReaction::default());
+        }
+    }
+
+    let weapon = properties
+        .weapon
+        .or(template.weapon.as_ref().map(|weapon| weapon.kind))
+        .unwrap_or(WeaponKind::Unarmed);
+    let (offset, scale) = match &template.weapon {
+        Some(weapon) => (Vec3::from(weapon.offset), weapon.scale),
+        None => (Vec3::new(50.0, 40.0, 0.1), 0.5),
+    };
+    commands.entity(request.entity).insert(EquippedWeapon(weapon));
+    equip_weapon(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        request.entity,
+        weapon,
+        offset,
+        scale,
+        &mut global_map,
+    );
+}
@@ -0,0 +1,590 @@
+use crate::Player;
+use crate::constants::*;
+use crate::difficulty::Difficulty;
+use crate::game_state::GameplaySet;
+use crate::level::level::{LevelEntity, LevelTransitionEvent};
+use crate::level::tiled::{LevelData, ObjectLayers};
+use crate::respawn::{PlayerDying, RespawnState};
+use crate::spawn_properties::SpawnProperties;
+use crate::unit_death::UnitDiedEvent;
+use crate::unit_template::SpawnUnitExt;
+use bevy::asset::{AssetLoader, LoadContext, io::Reader};
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use tiled::PropertyValue;
+
+/// Waves to fight through, loaded from `assets/waves/<id>.waves.ron` or built from
+/// the objects of the Spawner layer
+#[derive(Asset, TypePath, Deserialize, Clone, Debug, Default)]
+pub struct WaveScript {
+    pub waves: Vec<Wave>,
+    /// Arena mode: start over from the first wave, harder each time, until the player dies
+    #[serde(default)]
+    pub endless: bool,
+    #[serde(default)]
+    pub ramp: DifficultyRamp,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct Wave {
+    /// Seconds before the first unit of the wave appears
+    #[serde(default)]
+    pub delay: f32,
+    pub groups: Vec<WaveGroup>,
+    #[serde(default)]
+    pub until: WaveCondition,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct WaveGroup {
+    pub template: String,
+    pub count: u32,
+    /// Seconds between two units of the group
+    #[serde(default)]
+    pub interval: f32,
+    /// Spawner objects to cycle through; empty uses all of them
+    #[serde(default)]
+    pub spawn_points: Vec<String>,
+}
+
+/// When a wave counts as done and the next one starts
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum WaveCondition {
+    /// Every unit of the wave is dead
+    #[default]
+    AllDead,
+    /// This many seconds after the wave's `delay`, whatever is left alive. The wave also waits
+    /// until all of its units have spawned, so groups with long intervals can stretch it.
+    Timer(f32),
+}
+
+/// How much harder each wave gets, as fractions added per wave
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct DifficultyRamp {
+    #[serde(default)]
+    pub hp_per_wave: f32,
+    #[serde(default)]
+    pub count_per_wave: f32,
+}
+
+impl Default for DifficultyRamp {
+    fn default() -> Self {
+        Self {
+            hp_per_wave: 0.1,
+            count_per_wave: 0.0,
+        }
+    }
+}
+
+impl DifficultyRamp {
+    /// HP and unit count multipliers for the zero-based wave number
+    pub fn scale(&self, wave_number: u32) -> (f32, f32) {
+        let step = wave_number as f32;
+        (1.0 + self.hp_per_wave * step, 1.0 + self.count_per_wave * step)
+    }
+}
+
+#[derive(Event, Clone, Copy)]
+pub struct WaveStartedEvent {
+    /// One-based count of waves started since the spawner began
+    pub wave: u32,
+}
+
+#[derive(Event, Clone, Copy)]
+pub struct WaveClearedEvent {
+    pub wave: u32,
+}
+
+/// All waves of a non-endless script are done
+#[derive(Event, Clone, Copy)]
+pub struct WavesCompletedEvent {
+    pub score: u32,
+}
+
+/// The player died while waves were running
+#[derive(Event, Clone, Copy)]
+pub struct WavesFailedEvent {
+    pub wave: u32,
+    pub score: u32,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WavePhase {
+    #[default]
+    Idle,
+    /// Waiting for the script asset to load
+    Loading,
+    Running,
+    Completed,
+    Failed,
+}
+
+struct PendingSpawn {
+    template: String,
+    position: Vec2,
+    /// Seconds after the wave started
+    at: f32,
+}
+
+/// Runs the wave script of the current map
+#[derive(Resource, Default)]
+pub struct WaveSpawner {
+    pub phase: WavePhase,
+    script: Option<WaveScript>,
+    script_handle: Option<Handle<WaveScript>>,
+    /// Spawner objects by name
+    spawn_points: HashMap<String, Vec2>,
+    /// Index into the script's waves
+    wave_index: usize,
+    /// Waves started so far, also the ramp step
+    pub wave_number: u32,
+    elapsed: f32,
+    pending: Vec<PendingSpawn>,
+    alive: Vec<Entity>,
+}
+
+/// Kills and cleared waves since the spawner started
+#[derive(Resource, Default)]
+pub struct ArenaScore {
+    pub score: u32,
+    pub kills: u32,
+}
+
+#[derive(Component)]
+struct WaveText;
+
+#[derive(Component)]
+struct ScoreText;
+
+pub struct WavePlugin;
+
+impl Plugin for WavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<WaveScript>()
+            .register_asset_loader(WaveScriptLoader)
+            .init_resource::<WaveSpawner>()
+            .init_resource::<ArenaScore>()
+            .add_event::<WaveStartedEvent>()
+            .add_event::<WaveClearedEvent>()
+            .add_event::<WavesCompletedEvent>()
+            .add_event::<WavesFailedEvent>()
+            .add_systems(Startup, (register_spawner_layer_system, setup_wave_ui))
+            .add_systems(
+                Update,
+                (
+                    reset_waves_on_level_change,
+                    start_loaded_script,
+                    (count_kills, run_waves).in_set(GameplaySet),
+                    update_wave_ui,
+                )
+                    .chain(),
+            );
+    }
+}
+
+fn register_spawner_layer_system(mut commands: Commands, mut object_layers: ResMut<ObjectLayers>) {
+    let load_spawners_system = commands.register_system(load_spawners);
+    object_layers
+        .loader_systems
+        .insert(WAVE_SPAWNER_LAYER.to_string(), load_spawners_system);
+    info!("Registered system for layer: {}", WAVE_SPAWNER_LAYER);
+}
+
+/// Reads spawn points from the Spawner layer, and the waves either from a `waves` script
+/// property or from `template`, `count`, `wave` and `delay` properties on the spawners
+fn load_spawners(
+    object_layers: Res<ObjectLayers>,
+    level_data: Res<LevelData>,
+    asset_server: Res<AssetServer>,
+    mut spawner: ResMut<WaveSpawner>,
+    mut score: ResMut<ArenaScore>,
+) {
+    let Some(objects) = object_layers.layer_data.get(WAVE_SPAWNER_LAYER) else {
+        return;
+    };
+
+    *spawner = WaveSpawner::default();
+    *score = ArenaScore::default();
+    let mut inline_waves: Vec<Wave> = Vec::new();
+    let mut endless = false;
+    for object in objects {
+        let position = level_data.geometry.object_to_world(Vec2::new(object.x, object.y));
+        spawner.spawn_points.insert(object.name.clone(), position);
+
+        let property = |key: &str| object.properties.get(key);
+        if let Some(PropertyValue::StringValue(path)) = property("waves") {
+            info!("Spawner '{}' runs wave script {}", object.name, path);
+            spawner.script_handle = Some(asset_server.load(path.clone()));
+        }
+        if let Some(PropertyValue::BoolValue(value)) = property("endless") {
+            endless |= *value;
+        }
+        let Some(PropertyValue::StringValue(template)) = property("template") else {
+            continue;
+        };
+        let wave = match property("wave") {
+            Some(PropertyValue::IntValue(wave)) if *wave >= 1 => *wave as usize,
+            _ => 1,
+        };
+        let count = match property("count") {
+            Some(PropertyValue::IntValue(count)) if *count >= 0 => *count as u32,
+            _ => 1,
+        };
+        if inline_waves.len() < wave {
+            inline_waves.resize_with(wave, Wave::default);
+        }
+        if let Some(PropertyValue::FloatValue(delay)) = property("delay") {
+            inline_waves[wave - 1].delay = *delay;
+        }
+        inline_waves[wave - 1].groups.push(WaveGroup {
+            template: template.clone(),
+            count,
+            interval: 0.5,
+            spawn_points: vec![object.name.clone()],
+        });
+    }
+
+    if spawner.script_handle.is_some() {
+        spawner.phase = WavePhase::Loading;
+    } else if !inline_waves.is_empty() {
+        info!("Spawner layer defines {} waves", inline_waves.len());
+        spawner.script = Some(WaveScript {
+            waves: inline_waves,
+            endless,
+            ramp: DifficultyRamp::default(),
+        });
+        spawner.phase = WavePhase::Running;
+        spawner.wave_index = 0;
+        spawner.pending.clear();
+    }
+}
+
+fn start_loaded_script(mut spawner: ResMut<WaveSpawner>, scripts: Res<Assets<WaveScript>>) {
+    if spawner.phase != WavePhase::Loading {
+        return;
+    }
+    let Some(script) = spawner
+        .script_handle
+        .as_ref()
+        .and_then(|handle| scripts.get(handle))
+        .cloned()
+    else {
+        return;
+    };
+    info!("Wave script loaded with {} waves", script.waves.len());
+    spawner.script = Some(script);
+    spawner.phase = WavePhase::Running;
+}
+
+fn count_kills(
+    mut died_events: EventReader<UnitDiedEvent>,
+    spawner: Res<WaveSpawner>,
+    mut score: ResMut<ArenaScore>,
+) {
+    for event in died_events.read() {
+        if spawner.alive.contains(&event.entity) {
+            score.kills += 1;
+            score.score += SCORE_PER_KILL;
+        }
+    }
+}
+
+fn run_waves(
+    mut commands: Commands,
+    mut spawner: ResMut<WaveSpawner>,
+    mut score: ResMut<ArenaScore>,
+    difficulty: Res<Difficulty>,
+    time: Res<Time>,
+    respawn: Res<RespawnState>,
+    unit_query: Query<()>,
+    player_query: Query<(), Or<(With<Player>, With<PlayerDying>)>>,
+    mut started_events: EventWriter<WaveStartedEvent>,
+    mut cleared_events: EventWriter<WaveClearedEvent>,
+    mut completed_events: EventWriter<WavesCompletedEvent>,
+    mut failed_events: EventWriter<WavesFailedEvent>,
+) {
+    if spawner.phase != WavePhase::Running {
+        return;
+    }
+    let Some(script) = spawner.script.clone() else {
+        return;
+    };
+    if script.waves.is_empty() {
+        spawner.phase = WavePhase::Completed;
+        return;
+    }
+
+    // Dying with lives or continues left respawns the hero, so only the final death fails
+    if player_query.is_empty() && respawn.lives == 0 && respawn.continues == 0 {
+        warn!("Player died on wave {}", spawner.wave_number);
+        spawner.phase = WavePhase::Failed;
+        failed_events.write(WavesFailedEvent {
+            wave: spawner.wave_number,
+            score: score.score,
+        });
+        return;
+    }
+
+    // A new wave starts when nothing of the previous one is left to spawn or wait for
+    if spawner.wave_number == 0 || spawner.elapsed < 0.0 {
+        let wave = &script.waves[spawner.wave_index];
+        let (hp_scale, count_scale) = script.ramp.scale(spawner.wave_number);
+        let count_scale = count_scale * difficulty.settings().wave_size;
+        spawner.wave_number += 1;
+        spawner.elapsed = 0.0;
+        spawner.pending = plan_spawns(wave, &spawner.spawn_points, count_scale);
+        info!(
+            "Wave {} starts: {} units, hp x{:.2}",
+            spawner.wave_number,
+            spawner.pending.len(),
+            hp_scale
+        );
+        started_events.write(WaveStartedEvent {
+            wave: spawner.wave_number,
+        });
+    }
+
+    let wave = &script.waves[spawner.wave_index];
+    let (hp_scale, _) = script.ramp.scale(spawner.wave_number - 1);
+    spawner.elapsed += time.delta_secs();
+    let elapsed = spawner.elapsed;
+
+    let (due, pending): (Vec<_>, Vec<_>) = spawner
+        .pending
+        .drain(..)
+        .partition(|spawn| spawn.at <= elapsed);
+    spawner.pending = pending;
+    // Units spawned this frame only exist once commands are applied, so prune first
+    spawner.alive.retain(|entity| unit_query.get(*entity).is_ok());
+    for spawn in due {
+        let properties = SpawnProperties {
+            hp_scale: Some(hp_scale),
+            ..Default::default()
+        };
+        let unit = commands
+            .spawn_unit_with(spawn.template, spawn.position, properties)
+            .insert(LevelEntity)
+            .id();
+        spawner.alive.push(unit);
+    }
+
+    let done = match wave.until {
+        WaveCondition::AllDead => spawner.pending.is_empty() && spawner.alive.is_empty(),
+        WaveCondition::Timer(seconds) => spawner.pending.is_empty() && elapsed >= wave.delay + seconds,
+    };
+    if !done {
+        return;
+    }
+
+    info!("Wave {} cleared", spawner.wave_number);
+    score.score += SCORE_PER_WAVE * spawner.wave_number;
+    cleared_events.write(WaveClearedEvent {
+        wave: spawner.wave_number,
+    });
+
+    spawner.wave_index += 1;
+    if spawner.wave_index >= script.waves.len() {
+        if !script.endless {
+            info!("All waves completed, score {}", score.score);
+            spawner.phase = WavePhase::Completed;
+            completed_events.write(WavesCompletedEvent { score: score.score });
+            return;
+        }
+        spawner.wave_index = 0;
+    }
+    // Marks the next wave to start on the next frame
+    spawner.elapsed = -1.0;
+}
+
+/// Lays out when and where every unit of a wave appears
+fn plan_spawns(wave: &Wave, spawn_points: &HashMap<String, Vec2>, count_scale: f32) -> Vec<PendingSpawn> {
+    let mut all_points: Vec<&String> = spawn_points.keys().collect();
+    all_points.sort();
+
+    let mut spawns = Vec::new();
+    for group in wave.groups.iter() {
+        let names: Vec<&String> = if group.spawn_points.is_empty() {
+            all_points.clone()
+        } else {
+            group.spawn_points.iter().collect()
+        };
+        let positions: Vec<Vec2> = names
+            .iter()
+            .filter_map(|name| {
+                let position = spawn_points.get(*name).copied();
+                if position.is_none() {
+                    warn!("Unknown spawn point '{}' in wave", name);
+                }
+                position
+            })
+            .collect();
+        if positions.is_empty() {
+            warn!("No spawn points for '{}' group, skipping", group.template);
+            continue;
+        }
+
+        // Scaling never drops a group entirely
+        let count = if group.count == 0 {
+            0
+        } else {
+            (group.count as f32 * count_scale).round().max(1.0) as u32
+        };
+        for index in 0..count {
+            spawns.push(PendingSpawn {
+                template: group.template.clone(),
+                position: positions[index as usize % positions.len()],
+                at: wave.delay + group.interval * index as f32,
+            });
+        }
+    }
+    spawns
+}
+
+fn reset_waves_on_level_change(
+    mut transition_events: EventReader<LevelTransitionEvent>,
+    mut spawner: ResMut<WaveSpawner>,
+) {
+    if transition_events.read().last().is_none() {
+        return;
+    }
+    // Spawned units are level entities and go away with the map
+    *spawner = WaveSpawner::default();
+}
+
+fn setup_wave_ui(mut commands: Commands) {
+    commands
+        .spawn(Node {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            top: Val::Px(60.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 32.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                WaveText,
+            ));
+            parent.spawn((
+                Text::new(""),
+                TextFont {
+                    font_size: 20.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(1.0, 0.84, 0.0)),
+                ScoreText,
+            ));
+        });
+}
+
+fn update_wave_ui(
+    spawner: Res<WaveSpawner>,
+    score: Res<ArenaScore>,
+    mut wave_text: Query<&mut Text, (With<WaveText>, Without<ScoreText>)>,
+    mut score_text: Query<&mut Text, (With<ScoreText>, Without<WaveText>)>,
+) {
+    if !spawner.is_changed() && !score.is_changed() {
+        return;
+    }
+    let (wave, points) = match spawner.phase {
+        WavePhase::Idle | WavePhase::Loading => (String::new(), String::new()),
+        WavePhase::Running => (
+            format!("Wave {}", spawner.wave_number),
+            format!("Score {}  Kills {}", score.score, score.kills),
+        ),
+        WavePhase::Completed => (
+            "Arena cleared".to_string(),
+            format!("Final score {}", score.score),
+        ),
+        WavePhase::Failed => (
+            format!("Fell on wave {}", spawner.wave_number),
+            format!("Final score {}", score.score),
+        ),
+    };
+    if let Ok(mut text) = wave_text.single_mut() {
+        text.0 = wave;
+    }
+    if let Ok(mut text) = score_text.single_mut() {
+        text.0 = points;
+    }
+}
+
+#[derive(Default)]
+pub struct WaveScriptLoader;
+
+impl AssetLoader for WaveScriptLoader {
+    type Asset = WaveScript;
+    type Settings = ();
+    type Error = std::io::Error;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &Self::Settings,
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        ron::de::from_bytes(&bytes).map_err(|e| {
+            std::io::Error::other(format!(
+                "Could not parse wave script {}: {}",
+                load_context.path().display(),
+                e
+            ))
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["waves.ron"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ramp_scales_per_wave() {
+        let ramp = DifficultyRamp {
+            hp_per_wave: 0.25,
+            count_per_wave: 0.5,
+        };
+        assert_eq!(ramp.scale(0), (1.0, 1.0));
+        assert_eq!(ramp.scale(2), (1.5, 2.0));
+    }
+
+    #[test]
+    fn spawns_cycle_through_points() {
+        let spawn_points = HashMap::from([
+            ("North".to_string(), Vec2::new(0.0, 100.0)),
+            ("South".to_string(), Vec2::new(0.0, -100.0)),
+        ]);
+        let wave = Wave {
+            delay: 2.0,
+            groups: vec![WaveGroup {
+                template: "enemy".to_string(),
+                count: 3,
+                interval: 1.0,
+                spawn_points: Vec::new(),
+            }],
+            until: WaveCondition::AllDead,
+        };
+
+        let spawns = plan_spawns(&wave, &spawn_points, 1.0);
+        let positions: Vec<Vec2> = spawns.iter().map(|spawn| spawn.position).collect();
+        let times: Vec<f32> = spawns.iter().map(|spawn| spawn.at).collect();
+        assert_eq!(
+            positions,
+            vec![Vec2::new(0.0, 100.0), Vec2::new(0.0, -100.0), Vec2::new(0.0, 100.0)]
+        );
+        assert_eq!(times, vec![2.0, 3.0, 4.0]);
+        assert_eq!(plan_spawns(&wave, &spawn_points, 2.0).len(), 6);
+    }
+}
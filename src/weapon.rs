@@ -6,6 +6,7 @@ use bevy::color::palettes::css::ORANGE_RED;
 use bevy::prelude::*;
 use bevy_transform_interpolation::prelude::TransformInterpolation;
 use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
 #[derive(Component, Default)]
 pub struct Weapon {
@@ -309,6 +310,51 @@ pub fn equip_axe(
     global_entities.player_weapon.insert(player_entity, axe);
 }
 
+/// Weapon a spawned unit starts with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum WeaponKind {
+    Sword,
+    Axe,
+    Unarmed,
+}
+
+impl WeaponKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sword" => Some(Self::Sword),
+            "axe" => Some(Self::Axe),
+            "none" | "unarmed" => Some(Self::Unarmed),
+            _ => None,
+        }
+    }
+}
+
+/// Weapon kind a unit was equipped with
+#[derive(Component, Debug, Clone, Copy)]
+pub struct EquippedWeapon(pub WeaponKind);
+
+/// Equip `unit` with the given weapon kind
+pub fn equip_weapon(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<ColorMaterial>>,
+    unit: Entity,
+    kind: WeaponKind,
+    offset: Vec3,
+    scale: f32,
+    global_entities: &mut ResMut<GlobalEntityMap>,
+) {
+    match kind {
+        WeaponKind::Sword => {
+            equip_sword(commands, meshes, materials, unit, offset, scale, global_entities)
+        }
+        WeaponKind::Axe => {
+            equip_axe(commands, meshes, materials, unit, offset, scale, global_entities, unit)
+        }
+        WeaponKind::Unarmed => {}
+    }
+}
+
 pub enum GearSet {
     LongSword,
     DoubleEdgeAxe,